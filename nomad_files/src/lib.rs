@@ -0,0 +1,527 @@
+// The rules for laying out migrations on disk, shared by nomad_migrations! and nomad's runtime tools
+extern crate regex;
+
+use regex::Regex;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::{Path, PathBuf};
+
+// A migration found on disk, along with the files holding its SQL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationFile {
+    pub version: u64,
+    pub name: String,
+    // The SQL applying the migration
+    pub path: PathBuf,
+    // The SQL reverting the migration, if there is one
+    pub down_path: Option<PathBuf>,
+}
+
+// A set of migrations found in a directory, and the namespace they belong to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Namespace {
+    // The directory's path relative to the migrations root, joined with '/',
+    // or None for migrations directly inside the root
+    pub name: Option<String>,
+    pub migrations: Vec<MigrationFile>,
+}
+
+#[derive(Debug)]
+pub enum FileError {
+    MissingDirectory(PathBuf),
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    NonUtf8Name(PathBuf),
+    InvalidName(PathBuf),
+    UnmatchedDown {
+        version: u64,
+        path: PathBuf,
+    },
+    DuplicateVersion {
+        version: u64,
+        first: PathBuf,
+        second: PathBuf,
+    },
+    // A namespace directory found where only migrations were expected
+    UnexpectedNamespace(PathBuf),
+    InvalidPattern {
+        pattern: String,
+        reason: String,
+    },
+    RepeatableMigration(PathBuf),
+    // A version that can't be read as a whole number, such as Flyway's 1.1
+    UnsupportedVersion {
+        version: String,
+        path: PathBuf,
+    },
+    // Several problems, found in one pass over a directory
+    Multiple(Vec<FileError>),
+}
+
+impl FileError {
+    // Each individual problem, flattening any Multiple errors
+    pub fn into_errors(self) -> Vec<FileError> {
+        match self {
+            Self::Multiple(errors) => errors.into_iter().flat_map(Self::into_errors).collect(),
+            err => vec![err],
+        }
+    }
+}
+
+impl Display for FileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingDirectory(path) => write!(f, "\"{}\" doesn't exist", path.display()),
+            Self::Io { path, source } => {
+                write!(f, "Failed to access \"{}\": {}", path.display(), source)
+            }
+            Self::NonUtf8Name(path) => write!(
+                f,
+                "\"{}\" is not a valid migration file name, names must be valid UTF-8",
+                path.display()
+            ),
+            Self::InvalidName(path) => {
+                write!(
+                    f,
+                    "\"{}\" is not a valid migration file name",
+                    path.display()
+                )
+            }
+            Self::UnmatchedDown { version, path } => write!(
+                f,
+                "Down migration \"{}\" has no matching up migration {}",
+                path.display(),
+                version
+            ),
+            Self::DuplicateVersion {
+                version,
+                first,
+                second,
+            } => write!(
+                f,
+                "\"{}\" and \"{}\" share version {}, versions must be unique",
+                first.display(),
+                second.display(),
+                version
+            ),
+            Self::UnexpectedNamespace(path) => write!(
+                f,
+                "\"{}\" is a directory of namespaced migrations, which must be read as namespaces",
+                path.display()
+            ),
+            Self::InvalidPattern { pattern, reason } => write!(
+                f,
+                "\"{}\" is not a valid migration file pattern, {}",
+                pattern, reason
+            ),
+            Self::RepeatableMigration(path) => write!(
+                f,
+                "\"{}\" is a repeatable migration, which nomad can't run",
+                path.display()
+            ),
+            Self::UnsupportedVersion { version, path } => write!(
+                f,
+                "\"{}\" has version {}, but nomad versions must be whole numbers",
+                path.display(),
+                version
+            ),
+            Self::Multiple(errors) => {
+                for (index, err) in errors.iter().enumerate() {
+                    if index > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{}", err)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for FileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+// The default file name pattern. Down migrations sit beside their up migration, as <version>_<name>.down.sql
+pub const DEFAULT_PATTERN: &str =
+    r"^(?P<version>\d+)[_\-.]*(?P<name>.*?)(?:\.up|(?P<down>\.down))?\.sql$";
+
+// A naming scheme for migrations, as used by nomad or another migration tool
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Convention {
+    // Files matching DEFAULT_PATTERN, alongside diesel's directories
+    #[default]
+    Nomad,
+    // V<version>__<name>.sql, with undo migrations as U<version>__<name>.sql.
+    // Repeatable R__<name>.sql migrations are skipped, and dotted versions like V1.1 are errors.
+    Flyway,
+    // <version>_<name>.up.sql and <version>_<name>.down.sql
+    GolangMigrate,
+    // A directory per migration, named <timestamp>_<name>, holding up.sql and down.sql
+    Diesel,
+}
+
+impl Convention {
+    // Look up a convention by the name used in nomad_migrations! and the nomad binary
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "nomad" => Some(Convention::Nomad),
+            "flyway" => Some(Convention::Flyway),
+            "golang-migrate" => Some(Convention::GolangMigrate),
+            "diesel" => Some(Convention::Diesel),
+            _ => None,
+        }
+    }
+
+    // The pattern matching migration files, or None if migrations only live in directories
+    pub fn pattern(&self) -> Option<&'static str> {
+        match self {
+            Convention::Nomad => Some(DEFAULT_PATTERN),
+            Convention::Flyway => {
+                Some(r"^(?:V|(?P<down>U))(?P<version>\d+(?:[._]\d+)*)__(?P<name>.*)\.sql$")
+            }
+            Convention::GolangMigrate => {
+                Some(r"^(?P<version>\d+)_(?P<name>.*)\.(?:up|(?P<down>down))\.sql$")
+            }
+            Convention::Diesel => None,
+        }
+    }
+
+    // Whether directories holding an up.sql are migrations, rather than namespaces
+    fn has_migration_dirs(&self) -> bool {
+        matches!(self, Convention::Nomad | Convention::Diesel)
+    }
+}
+
+// How migrations are picked out of a directory
+#[derive(Debug, Clone)]
+pub struct ReadOptions {
+    convention: Convention,
+    pattern: Option<Regex>,
+    ignore: Vec<String>,
+    strict: bool,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        ReadOptions {
+            convention: Convention::Nomad,
+            pattern: Some(Regex::new(DEFAULT_PATTERN).expect("Literal regex is known good")),
+            ignore: Vec::new(),
+            strict: false,
+        }
+    }
+}
+
+impl ReadOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Read migrations named by another tool's convention, replacing any pattern set before
+    pub fn with_convention(mut self, convention: Convention) -> Self {
+        self.convention = convention;
+        self.pattern = convention
+            .pattern()
+            .map(|pattern| Regex::new(pattern).expect("Literal regex is known good"));
+        self
+    }
+
+    // Match migration files with a custom regex, in place of the convention's pattern.
+    // The pattern must capture a `version`, and may capture a `name`.
+    // Files where it also captures `down` are down migrations.
+    pub fn with_pattern(mut self, pattern: &str) -> Result<Self, FileError> {
+        let invalid = |reason: String| FileError::InvalidPattern {
+            pattern: pattern.to_owned(),
+            reason,
+        };
+
+        let regex = Regex::new(pattern).map_err(|err| invalid(err.to_string()))?;
+        if !regex.capture_names().any(|name| name == Some("version")) {
+            return Err(invalid("it has no `version` capture group".to_owned()));
+        }
+
+        self.pattern = Some(regex);
+        Ok(self)
+    }
+
+    // Skip files and directories with names matching a glob, where * matches any run of characters
+    // and ? matches any single character
+    pub fn with_ignore<S: Into<String>>(mut self, glob: S) -> Self {
+        self.ignore.push(glob.into());
+        self
+    }
+
+    // Report files that don't match the pattern as errors, instead of skipping them
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    fn is_ignored(&self, file_name: &str) -> bool {
+        self.ignore.iter().any(|glob| {
+            glob_match(
+                &glob.chars().collect::<Vec<_>>(),
+                &file_name.chars().collect::<Vec<_>>(),
+            )
+        })
+    }
+}
+
+fn glob_match(glob: &[char], name: &[char]) -> bool {
+    match glob.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|skip| glob_match(rest, &name[skip..])),
+        Some((&c, rest)) => match name.split_first() {
+            Some((&n, name)) if c == '?' || c == n => glob_match(rest, name),
+            _ => false,
+        },
+    }
+}
+
+// A single directory entry, holding either half of a migration
+enum Entry {
+    Up(MigrationFile),
+    Down(u64, PathBuf),
+    // A directory of migrations belonging to another namespace
+    Namespace(PathBuf),
+    // Anything else, left alone outside of strict mode
+    Skipped,
+}
+
+fn parse_entry(path: PathBuf, options: &ReadOptions) -> Result<Entry, FileError> {
+    // Diesel's layout, a directory per migration named <timestamp>_<name> containing up.sql and down.sql
+    let diesel_regex = Regex::new(r"^(?P<version>\d{4}-\d{2}-\d{2}-\d{6}|\d+)_(?P<name>.*)$")
+        .expect("Literal regex is known good");
+
+    let skip = |err: FileError| {
+        if options.strict {
+            Err(err)
+        } else {
+            Ok(Entry::Skipped)
+        }
+    };
+
+    let file_name = match path.file_name().map(|name| name.to_str()) {
+        Some(Some(file_name)) => file_name,
+        _ => return skip(FileError::NonUtf8Name(path)),
+    };
+
+    // Hidden files, like .gitkeep, are never migrations
+    if options.is_ignored(file_name) || (file_name.starts_with('.') && !options.strict) {
+        return Ok(Entry::Skipped);
+    }
+
+    // Diesel migration directories hold an up.sql, anything else is a namespace
+    let is_dir = path.is_dir();
+    if is_dir && !(options.convention.has_migration_dirs() && path.join("up.sql").exists()) {
+        return Ok(Entry::Namespace(path));
+    }
+
+    // Repeatable migrations have no version, and are rerun whenever they change,
+    // so can't be run by nomad. They're skipped like any other file, or reported in strict mode.
+    if options.convention == Convention::Flyway
+        && file_name.starts_with("R__")
+        && file_name.ends_with(".sql")
+    {
+        return skip(FileError::RepeatableMigration(path));
+    }
+
+    let regex = match (is_dir, &options.pattern) {
+        (true, _) => &diesel_regex,
+        (false, Some(pattern)) => pattern,
+        (false, None) => return skip(FileError::InvalidName(path)),
+    };
+    let captures = match regex.captures(file_name) {
+        Some(captures) => captures,
+        None => return skip(FileError::InvalidName(path)),
+    };
+
+    // Diesel versions are timestamps with the separators removed.
+    // A name matching the pattern is meant to be a migration, so a bad version is always an error.
+    // Custom patterns may match without capturing a version at all
+    let version = match captures.name("version").map(|version| version.as_str()) {
+        Some(version) => version,
+        None => return Err(FileError::InvalidName(path)),
+    };
+    let version = match version.replace('-', "").parse::<u64>() {
+        Ok(version) => version,
+        // Flyway allows versions like 1.1 and 1_1, which have no whole number equivalent
+        Err(_) if version.contains(|c| c == '.' || c == '_') => {
+            return Err(FileError::UnsupportedVersion {
+                version: version.to_owned(),
+                path,
+            })
+        }
+        Err(_) => return Err(FileError::InvalidName(path)),
+    };
+    let name = captures.name("name").map_or("", |m| m.as_str()).to_owned();
+
+    if captures.name("down").is_some() {
+        return Ok(Entry::Down(version, path));
+    }
+
+    Ok(Entry::Up(if is_dir {
+        let down_path = path.join("down.sql");
+
+        MigrationFile {
+            version,
+            name,
+            path: path.join("up.sql"),
+            down_path: if down_path.exists() {
+                Some(down_path)
+            } else {
+                None
+            },
+        }
+    } else {
+        MigrationFile {
+            version,
+            name,
+            path,
+            down_path: None,
+        }
+    }))
+}
+
+// Find every migration in a directory with the default options, sorted by version
+pub fn read_dir(path: &Path) -> Result<Vec<MigrationFile>, FileError> {
+    ReadOptions::default().read_dir(path)
+}
+
+// Find every migration in a directory and its namespace subdirectories with the default options
+pub fn read_namespaces(path: &Path) -> Result<Vec<Namespace>, FileError> {
+    ReadOptions::default().read_namespaces(path)
+}
+
+impl ReadOptions {
+    // Find every migration in a directory, sorted by version.
+    // Every problem found is reported, as a single error or as FileError::Multiple.
+    pub fn read_dir(&self, path: &Path) -> Result<Vec<MigrationFile>, FileError> {
+        let mut errors = Vec::new();
+        let (migrations, namespaces) = self.read_entries(path, &mut errors);
+
+        errors.extend(namespaces.into_iter().map(FileError::UnexpectedNamespace));
+
+        finish(migrations, errors)
+    }
+
+    // Find every migration in a directory and its subdirectories, each subdirectory being its own namespace.
+    // Namespaces are sorted by name, and only included when they hold at least one migration.
+    pub fn read_namespaces(&self, path: &Path) -> Result<Vec<Namespace>, FileError> {
+        let mut errors = Vec::new();
+        let mut namespaces = Vec::new();
+        let mut pending = vec![(None, path.to_owned())];
+
+        while let Some((name, path)) = pending.pop() {
+            let (migrations, children) = self.read_entries(&path, &mut errors);
+
+            for child in children {
+                // parse_entry has already checked the name is UTF-8
+                let child_name = child
+                    .file_name()
+                    .and_then(|child_name| child_name.to_str())
+                    .unwrap_or_default();
+                let child_name = match &name {
+                    Some(name) => format!("{}/{}", name, child_name),
+                    None => child_name.to_owned(),
+                };
+
+                pending.push((Some(child_name), child));
+            }
+
+            if !migrations.is_empty() {
+                namespaces.push(Namespace { name, migrations });
+            }
+        }
+
+        namespaces.sort_by(|a, b| a.name.cmp(&b.name));
+
+        finish(namespaces, errors)
+    }
+
+    // Read a single directory's migrations, sorted by version, and its namespace subdirectories.
+    // Problems are added to `errors`, and reading carries on past them wherever it can.
+    fn read_entries(
+        &self,
+        path: &Path,
+        errors: &mut Vec<FileError>,
+    ) -> (Vec<MigrationFile>, Vec<PathBuf>) {
+        let mut migrations = Vec::new();
+        let mut downs = Vec::new();
+        let mut namespaces = Vec::new();
+
+        if !path.exists() {
+            errors.push(FileError::MissingDirectory(path.to_owned()));
+            return (migrations, namespaces);
+        }
+
+        let io_error = |source| FileError::Io {
+            path: path.to_owned(),
+            source,
+        };
+
+        let entries = match path.read_dir() {
+            Ok(entries) => entries,
+            Err(source) => {
+                errors.push(io_error(source));
+                return (migrations, namespaces);
+            }
+        };
+
+        for entry in entries {
+            match entry
+                .map_err(io_error)
+                .and_then(|entry| parse_entry(entry.path(), self))
+            {
+                Ok(Entry::Up(migration)) => migrations.push(migration),
+                Ok(Entry::Down(version, path)) => downs.push((version, path)),
+                Ok(Entry::Namespace(path)) => namespaces.push(path),
+                Ok(Entry::Skipped) => {}
+                Err(err) => errors.push(err),
+            }
+        }
+
+        for (version, path) in downs {
+            match migrations
+                .iter_mut()
+                .find(|migration| migration.version == version)
+            {
+                Some(migration) => migration.down_path = Some(path),
+                None => errors.push(FileError::UnmatchedDown { version, path }),
+            }
+        }
+
+        migrations.sort_by(|a, b| a.version.cmp(&b.version).then_with(|| a.path.cmp(&b.path)));
+        namespaces.sort();
+
+        for pair in migrations.windows(2) {
+            if pair[0].version == pair[1].version {
+                errors.push(FileError::DuplicateVersion {
+                    version: pair[0].version,
+                    first: pair[0].path.clone(),
+                    second: pair[1].path.clone(),
+                });
+            }
+        }
+
+        (migrations, namespaces)
+    }
+}
+
+fn finish<T>(value: T, mut errors: Vec<FileError>) -> Result<T, FileError> {
+    match errors.len() {
+        0 => Ok(value),
+        1 => Err(errors.remove(0)),
+        _ => Err(FileError::Multiple(errors)),
+    }
+}
@@ -0,0 +1,114 @@
+use nomad_files::{Convention, ReadOptions};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{bracketed, Error, Ident, LitBool, LitStr, Token};
+
+// The arguments to nomad_migrations! and nomad_namespaces!, either a lone path literal
+// or key-value pairs such as `path = "migrations", ignore = ["README.md"]`
+pub(crate) struct Args {
+    pub path: LitStr,
+    convention: Option<LitStr>,
+    pattern: Option<LitStr>,
+    ignore: Vec<LitStr>,
+    strict: bool,
+}
+
+impl Args {
+    // The options for reading the directory, with errors pointing at the offending argument
+    pub fn read_options(&self) -> Result<ReadOptions, Error> {
+        let mut options = ReadOptions::new().with_strict(self.strict);
+
+        if let Some(convention) = &self.convention {
+            options = options.with_convention(
+                Convention::from_name(&convention.value()).ok_or_else(|| {
+                    Error::new(
+                        convention.span(),
+                        "Unknown convention, expected \"nomad\", \"flyway\", \"golang-migrate\" or \"diesel\"",
+                    )
+                })?,
+            );
+        }
+
+        if let Some(pattern) = &self.pattern {
+            options = options
+                .with_pattern(&pattern.value())
+                .map_err(|err| Error::new(pattern.span(), err))?;
+        }
+
+        for glob in &self.ignore {
+            options = options.with_ignore(glob.value());
+        }
+
+        Ok(options)
+    }
+}
+
+impl Parse for Args {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(LitStr) {
+            return Ok(Args {
+                path: input.parse()?,
+                convention: None,
+                pattern: None,
+                ignore: Vec::new(),
+                strict: false,
+            });
+        }
+
+        let mut path = None;
+        let mut convention = None;
+        let mut pattern = None;
+        let mut ignore = None;
+        let mut strict = None;
+
+        while !input.is_empty() {
+            let key: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+
+            let duplicate = || Error::new(key.span(), format!("`{}` is given more than once", key));
+
+            match key.to_string().as_str() {
+                "path" if path.is_some() => return Err(duplicate()),
+                "path" => path = Some(input.parse::<LitStr>()?),
+                "convention" if convention.is_some() => return Err(duplicate()),
+                "convention" => convention = Some(input.parse::<LitStr>()?),
+                "pattern" if pattern.is_some() => return Err(duplicate()),
+                "pattern" => pattern = Some(input.parse::<LitStr>()?),
+                "ignore" if ignore.is_some() => return Err(duplicate()),
+                "ignore" => {
+                    let globs;
+                    bracketed!(globs in input);
+                    ignore = Some(
+                        Punctuated::<LitStr, Token![,]>::parse_terminated(&globs)?
+                            .into_iter()
+                            .collect(),
+                    );
+                }
+                "strict" if strict.is_some() => return Err(duplicate()),
+                "strict" => strict = Some(input.parse::<LitBool>()?.value),
+                _ => {
+                    return Err(Error::new(
+                        key.span(),
+                        format!(
+                            "Unknown argument `{}`, expected `path`, `convention`, `pattern`, `ignore` or `strict`",
+                            key
+                        ),
+                    ))
+                }
+            }
+
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+        }
+
+        Ok(Args {
+            path: path.ok_or_else(|| input.error("Missing the `path` argument"))?,
+            convention,
+            pattern,
+            ignore: ignore.unwrap_or_default(),
+            strict: strict.unwrap_or(false),
+        })
+    }
+}
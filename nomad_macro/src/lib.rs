@@ -1,62 +1,162 @@
+extern crate nomad_files;
 extern crate proc_macro;
 extern crate quote;
-extern crate regex;
 extern crate syn;
 
 use proc_macro::TokenStream;
 use quote::quote;
-use regex::Regex;
 use std::env;
-use std::fs;
 use std::path::PathBuf;
-use syn::{parse_macro_input, LitStr};
+use syn::{parse_macro_input, Error, LitStr};
 
+mod args;
 mod migration;
+use args::Args;
 use migration::Migration;
 
+// Merge problems into a single error, so that the compiler reports every one of them
+fn combine(errors: impl IntoIterator<Item = Error>) -> Option<Error> {
+    errors
+        .into_iter()
+        .fold(None, |combined, err| match combined {
+            Some(mut combined) => {
+                combined.combine(err);
+                Some(combined)
+            }
+            None => Some(err),
+        })
+}
+
+// Report problems reading the migrations directory against the path literal
+fn file_errors(input: &LitStr, err: nomad_files::FileError) -> Error {
+    combine(
+        err.into_errors()
+            .into_iter()
+            .map(|err| Error::new(input.span(), err)),
+    )
+    .expect("A FileError holds at least one error")
+}
+
+// Refer to each migration file by its full path, for include_str!
+fn load(input: &LitStr, files: Vec<nomad_files::MigrationFile>) -> Result<Vec<Migration>, Error> {
+    let mut errors = Vec::new();
+    let mut utf8_path = |path: PathBuf| match path.to_str() {
+        Some(utf8_path) => utf8_path.to_owned(),
+        None => {
+            errors.push(Error::new(
+                input.span(),
+                format!("\"{}\" is not a valid UTF-8 path", path.display()),
+            ));
+            String::new()
+        }
+    };
+
+    let mut sorted_migrations = Vec::new();
+    for file in files {
+        sorted_migrations.push(Migration {
+            version: file.version,
+            name: file.name,
+            path: utf8_path(file.path),
+            down_path: file.down_path.map(&mut utf8_path),
+        });
+    }
+
+    if let Some(err) = combine(errors) {
+        return Err(err);
+    }
+
+    sorted_migrations.sort();
+    Ok(sorted_migrations)
+}
+
+// Expand to every error at once, wrapped in a block as the macros are used as expressions
+fn compile_errors(err: Error) -> quote::__private::TokenStream {
+    let errors = err.to_compile_error();
+    quote! { { #errors } }
+}
+
+fn migrations_path(input: &LitStr) -> Result<PathBuf, Error> {
+    let crate_root = env::var("CARGO_MANIFEST_DIR")
+        .map_err(|_| Error::new(input.span(), "CARGO_MANIFEST_DIR is not set"))?;
+
+    Ok(PathBuf::from(crate_root).join(input.value()))
+}
+
+// Embed every migration in a directory, given either as a lone path or as key-value arguments:
+//   path = "migrations"      the directory, relative to the crate root
+//   convention = "flyway"    another tool's naming scheme: "flyway", "golang-migrate" or "diesel"
+//   pattern = "..."          regex matching migration file names, see nomad_files::DEFAULT_PATTERN
+//   ignore = ["README.md"]   globs of file and directory names to skip
+//   strict = true            report files not matching the pattern as errors, instead of skipping them
 #[proc_macro]
 pub fn nomad_migrations(input: TokenStream) -> TokenStream {
-    let file_regex = Regex::new(r"^(\d+).*\.sql$").expect("Literal regex is known good");
+    let args = parse_macro_input!(input as Args);
 
-    let input = parse_macro_input!(input as LitStr);
-    let crate_root = env::var("CARGO_MANIFEST_DIR").unwrap();
-    let path = PathBuf::from(crate_root).join(input.value());
+    migrations(&args).unwrap_or_else(compile_errors).into()
+}
 
-    if !path.exists() {
-        panic!("\"{}\" doesn't exist", path.display());
-    }
+fn migrations(args: &Args) -> Result<quote::__private::TokenStream, Error> {
+    let input = &args.path;
+    let path = migrations_path(input)?;
 
-    let mut sorted_migrations = Vec::new();
-    for entry in path.read_dir().expect("Failed to get children") {
-        let entry = entry.expect("Could not load child");
-        let entry_path = entry.path();
+    let files = args
+        .read_options()?
+        .read_dir(&path)
+        .map_err(|err| file_errors(input, err))?;
+    let sorted_migrations = load(input, files)?;
 
-        let filename = entry_path
-            .file_name()
-            .unwrap()
-            .to_str()
-            .expect("Migration filenames must be valid UTF-8");
+    // We've sorted the migrations ahead of time, so the invariant is preserved
+    Ok(quote! { unsafe { ::nomad::OrderedMigrations::new_unsafe([#(#sorted_migrations), *]) } })
+}
 
-        let captures = file_regex.captures(filename).expect(&format!(
-            "\"{}\" is not a valid migration file name",
-            filename
-        ));
+// Embed a directory whose subdirectories each hold a namespace's migrations, such as migrations/core/.
+// Nested subdirectories are namespaced by their path, like "core/auth",
+// and migrations directly inside the directory belong to nomad's default namespace.
+// Expands to an array of (namespace, migrations) pairs, sorted by namespace,
+// ready to pass to MigrationRegistry::register_all. Takes the same arguments as nomad_migrations!.
+#[proc_macro]
+pub fn nomad_namespaces(input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(input as Args);
 
-        sorted_migrations.push(Migration {
-            version: captures
-                .get(1)
-                .expect("Version number missing")
-                .as_str()
-                .parse::<u64>()
-                .expect("Unable to parse version number"),
-            sql: fs::read_to_string(entry_path).expect("Failed to read migration SQL"),
+    namespaces(&args).unwrap_or_else(compile_errors).into()
+}
+
+fn namespaces(args: &Args) -> Result<quote::__private::TokenStream, Error> {
+    let input = &args.path;
+    let path = migrations_path(input)?;
+    let options = args.read_options()?;
+
+    let mut errors = Vec::new();
+    let mut namespaces = Vec::new();
+    for namespace in options
+        .read_namespaces(&path)
+        .map_err(|err| file_errors(input, err))?
+    {
+        let name = match namespace.name {
+            Some(name) => quote! { #name },
+            None => quote! { ::nomad::DEFAULT_NAMESPACE },
+        };
+
+        let sorted_migrations = match load(input, namespace.migrations) {
+            Ok(sorted_migrations) => sorted_migrations,
+            Err(err) => {
+                errors.push(err);
+                continue;
+            }
+        };
+
+        // Each namespace has a different number of migrations, so they're borrowed as slices
+        namespaces.push(quote! {
+            (#name, {
+                const MIGRATIONS: &[::nomad::Migration<'static>] = &[#(#sorted_migrations), *];
+                unsafe { ::nomad::OrderedMigrations::new_unsafe(MIGRATIONS) }
+            })
         });
     }
 
-    sorted_migrations.sort();
+    if let Some(err) = combine(errors) {
+        return Err(err);
+    }
 
-    // We've sorted the migrations ahead of time, so the invariant is preserved
-    TokenStream::from(
-        quote! { unsafe { ::nomad::OrderedMigrations::new_unsafe([#(#sorted_migrations), *]) } },
-    )
+    Ok(quote! { [#(#namespaces), *] })
 }
@@ -3,7 +3,14 @@ use quote::{quote, ToTokens};
 // Wraps a migration as parsed from the disk.
 pub(crate) struct Migration {
     pub version: u64,
-    pub sql: String,
+    pub name: String,
+    pub path: String,
+    pub down_path: Option<String>,
+}
+
+// Embed a file with include_str!, so that cargo rebuilds the crate when it changes
+fn include(path: &str) -> quote::__private::TokenStream {
+    quote! { ::std::include_str!(#path) }
 }
 
 impl PartialEq for Migration {
@@ -28,9 +35,24 @@ impl Ord for Migration {
 impl ToTokens for Migration {
     fn to_tokens(&self, tokens: &mut quote::__private::TokenStream) {
         let version = &self.version;
-        let sql = &self.sql;
+        let name = &self.name;
+        let sql = include(&self.path);
+        let down = match &self.down_path {
+            Some(down_path) => {
+                let down = include(down_path);
+                quote! { ::std::option::Option::Some(::std::borrow::Cow::Borrowed(#down)) }
+            }
+            None => quote! { ::std::option::Option::None },
+        };
 
-        let tok = quote! { ::nomad::Migration { version: #version, sql: #sql } };
+        let tok = quote! {
+            ::nomad::Migration {
+                version: #version,
+                name: ::std::borrow::Cow::Borrowed(#name),
+                sql: ::std::borrow::Cow::Borrowed(#sql),
+                down: #down,
+            }
+        };
 
         tok.to_tokens(tokens)
     }
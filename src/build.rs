@@ -0,0 +1,156 @@
+// Helpers for build scripts, either tracking the directories read by nomad_migrations! and nomad_namespaces!,
+// or embedding migrations without the macros
+use nomad_files::{FileError, MigrationFile, ReadOptions};
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Rebuild the crate whenever anything in a migrations directory changes.
+// The macros embed each file they find, so edits to those files are already tracked,
+// but cargo can't see files being added to or removed from the directory without this.
+// The path is relative to the crate root, matching the path given to the macro.
+pub fn rerun_if_migrations_change<P: AsRef<Path>>(dir: P) {
+    // Cargo scans a directory given to rerun-if-changed for changes to anything inside it
+    println!("cargo:rerun-if-changed={}", dir.as_ref().display());
+}
+
+// The file written by embed_migrations, unless EmbedMigrations::with_file_name says otherwise
+pub const DEFAULT_EMBED_FILE: &str = "nomad_migrations.rs";
+
+// Embed a directory of migrations from a build script, writing nomad_migrations.rs to OUT_DIR.
+// Include it in the crate with
+//   mod migrations { include!(concat!(env!("OUT_DIR"), "/nomad_migrations.rs")); }
+// which defines MIGRATIONS, a static array of every migration, and migrations(), returning them as a set.
+// Returns the path of the written file.
+pub fn embed_migrations<P: AsRef<Path>>(dir: P) -> Result<PathBuf, FileError> {
+    EmbedMigrations::new(dir).write()
+}
+
+// Embeds migrations from a build script, as embed_migrations does, with more control over what is read and written
+pub struct EmbedMigrations {
+    dir: PathBuf,
+    options: ReadOptions,
+    file_name: String,
+    out_dir: Option<PathBuf>,
+}
+
+impl EmbedMigrations {
+    // Relative directories are found from the crate root, but any directory can be given,
+    // including one inside OUT_DIR holding migrations generated by an earlier build step
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        EmbedMigrations {
+            dir: dir.as_ref().to_owned(),
+            options: ReadOptions::default(),
+            file_name: DEFAULT_EMBED_FILE.to_owned(),
+            out_dir: None,
+        }
+    }
+
+    // Read the directory with the same options nomad_migrations! accepts
+    pub fn with_options(mut self, options: ReadOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    // Name the written file, so that several directories can be embedded by one crate
+    pub fn with_file_name<S: Into<String>>(mut self, file_name: S) -> Self {
+        self.file_name = file_name.into();
+        self
+    }
+
+    // Write to a directory other than OUT_DIR
+    pub fn with_out_dir<P: AsRef<Path>>(mut self, out_dir: P) -> Self {
+        self.out_dir = Some(out_dir.as_ref().to_owned());
+        self
+    }
+
+    // Write the module, and tell cargo to rerun the build script when any of the migrations change.
+    // Fails if no output directory was given and OUT_DIR isn't set, as happens outside of build scripts.
+    pub fn write(&self) -> Result<PathBuf, FileError> {
+        let dir = match env::var_os("CARGO_MANIFEST_DIR") {
+            Some(crate_root) if self.dir.is_relative() => PathBuf::from(crate_root).join(&self.dir),
+            _ => self.dir.clone(),
+        };
+        let out_dir = match (&self.out_dir, env::var_os("OUT_DIR")) {
+            (Some(out_dir), _) => out_dir.clone(),
+            (None, Some(out_dir)) => PathBuf::from(out_dir),
+            (None, None) => {
+                return Err(FileError::Io {
+                    path: PathBuf::from(&self.file_name),
+                    source: io::Error::new(
+                        io::ErrorKind::NotFound,
+                        "OUT_DIR is not set, is this running in a build script?",
+                    ),
+                })
+            }
+        };
+
+        let files = self.options.read_dir(&dir)?;
+
+        rerun_if_migrations_change(&dir);
+        for file in &files {
+            println!("cargo:rerun-if-changed={}", file.path.display());
+            if let Some(down_path) = &file.down_path {
+                println!("cargo:rerun-if-changed={}", down_path.display());
+            }
+        }
+
+        let path = out_dir.join(&self.file_name);
+        fs::write(&path, module(&files)?).map_err(|source| FileError::Io {
+            path: path.clone(),
+            source,
+        })?;
+
+        Ok(path)
+    }
+}
+
+// The source of the generated module. Files are embedded with include_str!,
+// so that edits to them rebuild the crate even when the build script doesn't rerun.
+fn module(files: &[MigrationFile]) -> Result<String, FileError> {
+    fn include(path: &Path) -> Result<String, FileError> {
+        match path.to_str() {
+            Some(path) => Ok(format!(
+                "::std::borrow::Cow::Borrowed(::std::include_str!({:?}))",
+                path
+            )),
+            None => Err(FileError::NonUtf8Name(path.to_owned())),
+        }
+    }
+
+    let mut module = format!(
+        "// Generated by nomad::build::embed_migrations\n\npub static MIGRATIONS: [::nomad::Migration<'static>; {}] = [\n",
+        files.len()
+    );
+
+    for file in files {
+        let down = match &file.down_path {
+            Some(down_path) => format!("::std::option::Option::Some({})", include(down_path)?),
+            None => "::std::option::Option::None".to_owned(),
+        };
+
+        // Writing to a String can't fail
+        let _ = write!(
+            module,
+            "    ::nomad::Migration {{\n        version: {},\n        name: ::std::borrow::Cow::Borrowed({:?}),\n        sql: {},\n        down: {},\n    }},\n",
+            file.version,
+            file.name,
+            include(&file.path)?,
+            down
+        );
+    }
+
+    module.push_str(
+        "];
+
+pub fn migrations() -> ::nomad::OrderedMigrations<'static, &'static [::nomad::Migration<'static>]> {
+    // nomad_files sorts migrations by version and rejects duplicates, so the invariant is preserved
+    unsafe { ::nomad::OrderedMigrations::new_unsafe(&MIGRATIONS) }
+}
+",
+    );
+
+    Ok(module)
+}
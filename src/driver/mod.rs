@@ -4,6 +4,11 @@ mod rusqlite;
 #[cfg(feature = "sqlx")]
 mod sqlx;
 
+// Scripts and schema snapshots use the SQLite statements without a driver,
+// so only its connection handling is gated on the SQLite drivers
+pub(crate) mod sqlite;
+
+use crate::{AppliedMigration, TrackingTable};
 use std::error::Error;
 
 pub trait Driver<'a>
@@ -19,7 +24,22 @@ where
     fn begin(&'a mut self) -> Result<Self::Transaction, Self::Error>;
 
     // Get the latest migrated version for a given namespace
-    fn latest_version(&mut self, namespace: &str) -> Result<Option<u64>, Self::Error>;
+    fn latest_version(
+        &mut self,
+        table: &TrackingTable<'_>,
+        namespace: &str,
+    ) -> Result<Option<u64>, Self::Error>;
+
+    // Get every migration applied in a given namespace, in ascending version order
+    fn applied_migrations(
+        &mut self,
+        table: &TrackingTable<'_>,
+        namespace: &str,
+    ) -> Result<Vec<AppliedMigration>, Self::Error>;
+
+    // Run an arbitrary query, returning every value as text.
+    // Queries should cast non-text columns to text, as not every driver can convert them.
+    fn query_rows(&mut self, sql: &str) -> Result<Vec<Vec<Option<String>>>, Self::Error>;
 }
 
 pub trait Transaction<'a, D: Driver<'a>> {
@@ -32,7 +52,26 @@ pub trait Transaction<'a, D: Driver<'a>> {
     // Update the latest migrated version for a given namespace
     fn push_latest_version(
         &mut self,
+        table: &TrackingTable<'_>,
+        namespace: &str,
+        version: u64,
+    ) -> Result<(), <D as Driver<'a>>::Error>;
+
+    // Record that a single migration has been applied in a given namespace
+    fn record_applied(
+        &mut self,
+        table: &TrackingTable<'_>,
+        namespace: &str,
+        migration: &AppliedMigration,
+    ) -> Result<(), <D as Driver<'a>>::Error>;
+
+    // Forget a migration that has been reverted in a given namespace,
+    // moving the latest migrated version back to the newest migration still applied
+    fn remove_applied(
+        &mut self,
+        table: &TrackingTable<'_>,
         namespace: &str,
         version: u64,
+        latest_version: Option<u64>,
     ) -> Result<(), <D as Driver<'a>>::Error>;
 }
@@ -1,20 +1,23 @@
-use crate::{Driver, Transaction};
+use super::sqlite::{self, TrackingConnection};
+use crate::{AppliedMigration, Driver, TrackingTable, Transaction};
 
-use rusqlite::{params, types::Type, Connection, Error, OptionalExtension, Row, NO_PARAMS};
-use std::array::TryFromSliceError;
-use std::convert::TryInto;
+use rusqlite::{params, types::ValueRef, Connection, Error, OptionalExtension, Row, NO_PARAMS};
 
-// SQL definition for the backing table
-const MIGRATION_BACKING_DEF: &'static str = r#"
-CREATE TABLE IF NOT EXISTS nomad_migrations (
-    namespace   text not null primary key,
-    version     blob not null
-) WITHOUT ROWID;
-"#;
+impl TrackingConnection for &Connection {
+    type Error = Error;
+
+    fn execute(&mut self, sql: &str) -> Result<(), Error> {
+        self.execute_batch(sql)
+    }
+
+    fn query_integer(&mut self, sql: &str) -> Result<Option<i64>, Error> {
+        self.query_row(sql, NO_PARAMS, |row| row.get(0)).optional()
+    }
+}
 
 // Ensure that the migration table exists for us to read from
-fn ensure_migration_table(conn: &Connection) -> Result<(), Error> {
-    conn.execute(MIGRATION_BACKING_DEF, NO_PARAMS).and(Ok(()))
+fn ensure_migration_table(mut conn: &Connection, table: &TrackingTable<'_>) -> Result<(), Error> {
+    sqlite::ensure_tracking_tables(&mut conn, table)
 }
 
 impl<'a> Driver<'a> for Connection {
@@ -25,25 +28,69 @@ impl<'a> Driver<'a> for Connection {
         self.transaction()
     }
 
-    fn latest_version(&mut self, namespace: &str) -> Result<Option<u64>, Self::Error> {
-        fn map_conv_err(err: TryFromSliceError) -> Error {
-            Error::FromSqlConversionFailure(0, Type::Blob, Box::new(err))
-        }
-
-        fn convert_row(row: &Row) -> Result<u64, Error> {
-            let blob = row.get_raw(0).as_blob()?.try_into().map_err(map_conv_err)?;
-
-            Ok(u64::from_le_bytes(blob))
-        }
-
-        ensure_migration_table(self)?;
+    fn latest_version(
+        &mut self,
+        table: &TrackingTable<'_>,
+        namespace: &str,
+    ) -> Result<Option<u64>, Self::Error> {
+        ensure_migration_table(self, table)?;
 
         self.query_row(
-            "SELECT version FROM nomad_migrations WHERE namespace = ?",
+            &sqlite::select_latest_version(table),
             params![namespace],
-            convert_row,
+            |row| row.get::<_, i64>(0),
         )
         .optional()
+        .map(|version| version.map(|version| version as u64))
+    }
+
+    fn applied_migrations(
+        &mut self,
+        table: &TrackingTable<'_>,
+        namespace: &str,
+    ) -> Result<Vec<AppliedMigration>, Self::Error> {
+        fn convert_row(row: &Row) -> Result<AppliedMigration, Error> {
+            Ok(AppliedMigration {
+                version: row.get::<_, i64>(0)? as u64,
+                name: row.get(1)?,
+                out_of_order: row.get(2)?,
+                checksum: row
+                    .get::<_, Option<i64>>(3)?
+                    .map(|checksum| checksum as u32),
+            })
+        }
+
+        ensure_migration_table(self, table)?;
+
+        let mut statement = self.prepare(&sqlite::select_applied(table))?;
+
+        let migrations = statement
+            .query_map(params![namespace], convert_row)?
+            .collect();
+
+        migrations
+    }
+
+    fn query_rows(&mut self, sql: &str) -> Result<Vec<Vec<Option<String>>>, Self::Error> {
+        let mut statement = self.prepare(sql)?;
+        let column_count = statement.column_count();
+
+        let rows = statement
+            .query_map(NO_PARAMS, |row| {
+                Ok((0..column_count)
+                    .map(|index| match row.get_raw(index) {
+                        ValueRef::Null => None,
+                        ValueRef::Integer(value) => Some(value.to_string()),
+                        ValueRef::Real(value) => Some(value.to_string()),
+                        ValueRef::Text(text) | ValueRef::Blob(text) => {
+                            Some(String::from_utf8_lossy(text).into_owned())
+                        }
+                    })
+                    .collect())
+            })?
+            .collect();
+
+        rows
     }
 }
 
@@ -56,17 +103,63 @@ impl<'a> Transaction<'a, Connection> for rusqlite::Transaction<'a> {
         self.execute_batch(sql)
     }
 
-    fn push_latest_version(&mut self, namespace: &str, version: u64) -> Result<(), Error> {
-        ensure_migration_table(self)?;
+    fn push_latest_version(
+        &mut self,
+        table: &TrackingTable<'_>,
+        namespace: &str,
+        version: u64,
+    ) -> Result<(), Error> {
+        ensure_migration_table(self, table)?;
 
-        let version_bytes = &version.to_le_bytes()[..];
+        self.execute(
+            &sqlite::upsert_latest_version(table),
+            params![namespace, version as i64],
+        )
+        .and(Ok(()))
+    }
+
+    fn record_applied(
+        &mut self,
+        table: &TrackingTable<'_>,
+        namespace: &str,
+        migration: &AppliedMigration,
+    ) -> Result<(), Error> {
+        ensure_migration_table(self, table)?;
 
         self.execute(
-            "INSERT INTO nomad_migrations(namespace, version) VALUES(?, ?) ON CONFLICT(namespace) DO UPDATE SET version = excluded.version",
+            &sqlite::insert_applied(table),
             params![
                 namespace,
-                version_bytes
-            ]
-        ).and(Ok(()))
+                migration.version as i64,
+                migration.name,
+                migration.out_of_order,
+                migration.checksum.map(i64::from)
+            ],
+        )
+        .and(Ok(()))
+    }
+
+    fn remove_applied(
+        &mut self,
+        table: &TrackingTable<'_>,
+        namespace: &str,
+        version: u64,
+        latest_version: Option<u64>,
+    ) -> Result<(), Error> {
+        ensure_migration_table(self, table)?;
+
+        self.execute(
+            &sqlite::delete_applied(table),
+            params![namespace, version as i64],
+        )?;
+
+        match latest_version {
+            Some(latest_version) => self.execute(
+                &sqlite::upsert_latest_version(table),
+                params![namespace, latest_version as i64],
+            ),
+            None => self.execute(&sqlite::delete_latest_version(table), params![namespace]),
+        }
+        .and(Ok(()))
     }
 }
@@ -0,0 +1,288 @@
+use crate::TrackingTable;
+
+// The current layout of the tracking tables.
+// Whenever the layout changes, bump this and add a matching step to `upgrade_step`.
+pub(crate) const LAYOUT_VERSION: i64 = 4;
+
+// The minimal set of operations needed to create and upgrade the tracking tables
+#[cfg(any(feature = "rusqlite", feature = "sqlx-sqlite"))]
+pub(crate) trait TrackingConnection {
+    type Error;
+
+    // Execute one or more statements which return no rows
+    fn execute(&mut self, sql: &str) -> Result<(), Self::Error>;
+
+    // Run a query returning at most a single integer
+    fn query_integer(&mut self, sql: &str) -> Result<Option<i64>, Self::Error>;
+}
+
+// Quote an identifier so that arbitrary table and schema names are safe to use
+fn quote(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+pub(crate) fn quote_literal(literal: &str) -> String {
+    format!("'{}'", literal.replace('\'', "''"))
+}
+
+fn qualify(table: &TrackingTable<'_>, name: &str) -> String {
+    match table.schema {
+        Some(schema) => format!("{}.{}", quote(schema), quote(name)),
+        None => quote(name),
+    }
+}
+
+fn meta_name(table: &TrackingTable<'_>) -> String {
+    format!("{}_meta", table.name)
+}
+
+// Every table nomad keeps in the tracking table's schema
+pub(crate) fn table_names(table: &TrackingTable<'_>) -> [String; 3] {
+    [
+        table.name.to_owned(),
+        table.history_name(),
+        meta_name(table),
+    ]
+}
+
+// Make sure the tracking tables exist and are laid out as this version of nomad expects
+#[cfg(any(feature = "rusqlite", feature = "sqlx-sqlite"))]
+pub(crate) fn ensure_tracking_tables<C: TrackingConnection>(
+    conn: &mut C,
+    table: &TrackingTable<'_>,
+) -> Result<(), C::Error> {
+    conn.execute(&format!(
+        "CREATE TABLE IF NOT EXISTS {} (key text not null primary key, value integer not null) WITHOUT ROWID;",
+        qualify(table, &meta_name(table))
+    ))?;
+
+    let layout = conn.query_integer(&format!(
+        "SELECT value FROM {} WHERE key = 'layout_version'",
+        qualify(table, &meta_name(table))
+    ))?;
+
+    let layout = match layout {
+        Some(LAYOUT_VERSION) => return Ok(()),
+        // Laid out by a newer version of nomad, which must not be downgraded
+        Some(layout) if layout > LAYOUT_VERSION => return Ok(()),
+        Some(layout) => layout,
+        None => detect_layout(conn, table)?,
+    };
+
+    if layout == 0 {
+        return conn.execute(&current_layout_def(table));
+    }
+
+    // Savepoints work both inside and outside of an existing transaction
+    conn.execute("SAVEPOINT nomad_upgrade")?;
+
+    match upgrade(conn, table, layout) {
+        Ok(()) => conn.execute("RELEASE nomad_upgrade"),
+        Err(err) => {
+            // The original error is more useful than any error from cleaning up
+            let _ = conn.execute("ROLLBACK TO nomad_upgrade");
+            let _ = conn.execute("RELEASE nomad_upgrade");
+
+            Err(err)
+        }
+    }
+}
+
+// Work out the layout of tracking tables created before the layout was recorded
+#[cfg(any(feature = "rusqlite", feature = "sqlx-sqlite"))]
+fn detect_layout<C: TrackingConnection>(
+    conn: &mut C,
+    table: &TrackingTable<'_>,
+) -> Result<i64, C::Error> {
+    let master = match table.schema {
+        Some(schema) => format!("{}.sqlite_master", quote(schema)),
+        None => "sqlite_master".to_owned(),
+    };
+
+    let mut table_exists = |name: &str| -> Result<bool, C::Error> {
+        let count = conn.query_integer(&format!(
+            "SELECT count(*) FROM {} WHERE type = 'table' AND name = {}",
+            master,
+            quote_literal(name)
+        ))?;
+
+        Ok(count.unwrap_or(0) > 0)
+    };
+
+    Ok(if !table_exists(table.name)? {
+        0
+    } else if !table_exists(&table.history_name())? {
+        1
+    } else {
+        2
+    })
+}
+
+// SQL creating the tracking tables in the current layout, if they don't already exist.
+// Versions are u64 but SQLite integers are i64, so they're stored as their two's complement bit pattern.
+pub(crate) fn current_layout_def(table: &TrackingTable<'_>) -> String {
+    format!(
+        r#"
+CREATE TABLE IF NOT EXISTS {meta} (key text not null primary key, value integer not null) WITHOUT ROWID;
+CREATE TABLE IF NOT EXISTS {backing} (
+    namespace   text not null primary key,
+    version     integer not null
+) WITHOUT ROWID;
+CREATE TABLE IF NOT EXISTS {history} (
+    namespace   text not null,
+    version     integer not null,
+    name        text not null default '',
+    out_of_order integer not null default 0,
+    checksum    integer,
+    PRIMARY KEY (namespace, version)
+) WITHOUT ROWID;
+INSERT INTO {meta}(key, value) VALUES('layout_version', {layout}) ON CONFLICT(key) DO NOTHING;
+"#,
+        meta = qualify(table, &meta_name(table)),
+        backing = qualify(table, table.name),
+        history = qualify(table, &table.history_name()),
+        layout = LAYOUT_VERSION,
+    )
+}
+
+#[cfg(any(feature = "rusqlite", feature = "sqlx-sqlite"))]
+fn upgrade<C: TrackingConnection>(
+    conn: &mut C,
+    table: &TrackingTable<'_>,
+    layout: i64,
+) -> Result<(), C::Error> {
+    for step in (layout + 1)..=LAYOUT_VERSION {
+        conn.execute(&upgrade_step(table, step))?;
+    }
+
+    conn.execute(&format!(
+        "INSERT INTO {}(key, value) VALUES('layout_version', {}) ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        qualify(table, &meta_name(table)),
+        LAYOUT_VERSION
+    ))
+}
+
+// SQL to move the tracking tables from the previous layout to the given layout
+#[cfg(any(feature = "rusqlite", feature = "sqlx-sqlite"))]
+fn upgrade_step(table: &TrackingTable<'_>, layout: i64) -> String {
+    let backing = qualify(table, table.name);
+    let history = qualify(table, &table.history_name());
+
+    match layout {
+        // The original backing table, storing versions as little-endian blobs
+        1 => format!(
+            r#"
+CREATE TABLE IF NOT EXISTS {} (
+    namespace   text not null primary key,
+    version     blob not null
+) WITHOUT ROWID;
+"#,
+            backing
+        ),
+        // Per-version history
+        2 => format!(
+            r#"
+CREATE TABLE IF NOT EXISTS {} (
+    namespace   text not null,
+    version     integer not null,
+    name        text not null default '',
+    out_of_order integer not null default 0,
+    PRIMARY KEY (namespace, version)
+) WITHOUT ROWID;
+"#,
+            history
+        ),
+        // Store the latest version as a plain integer
+        3 => {
+            let upgraded = qualify(table, &format!("{}_upgrade", table.name));
+
+            format!(
+                r#"
+CREATE TABLE {upgraded} (
+    namespace   text not null primary key,
+    version     integer not null
+) WITHOUT ROWID;
+INSERT INTO {upgraded}(namespace, version) SELECT namespace, {version} FROM {backing};
+DROP TABLE {backing};
+ALTER TABLE {upgraded} RENAME TO {name};
+"#,
+                upgraded = upgraded,
+                version = blob_to_integer("version"),
+                backing = backing,
+                name = quote(table.name),
+            )
+        }
+        // Checksum the SQL of each applied migration
+        4 => format!("ALTER TABLE {} ADD COLUMN checksum integer;", history),
+        _ => unreachable!("Unknown tracking table layout {}", layout),
+    }
+}
+
+// SQL expression decoding an 8 byte little-endian blob column to an integer.
+// SQLite has no built in way to do this, so each byte is decoded from the blob's hex representation.
+#[cfg(any(feature = "rusqlite", feature = "sqlx-sqlite"))]
+fn blob_to_integer(column: &str) -> String {
+    let nibble = |index: usize| {
+        format!(
+            "(instr('0123456789ABCDEF', substr(hex({}), {}, 1)) - 1)",
+            column,
+            index + 1
+        )
+    };
+
+    (0..8)
+        .map(|byte| {
+            format!(
+                "(({} * 16 + {}) << {})",
+                nibble(byte * 2),
+                nibble(byte * 2 + 1),
+                byte * 8
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+#[cfg(any(feature = "rusqlite", feature = "sqlx-sqlite"))]
+pub(crate) fn select_latest_version(table: &TrackingTable<'_>) -> String {
+    format!(
+        "SELECT version FROM {} WHERE namespace = ?",
+        qualify(table, table.name)
+    )
+}
+
+pub(crate) fn upsert_latest_version(table: &TrackingTable<'_>) -> String {
+    format!(
+        "INSERT INTO {}(namespace, version) VALUES(?, ?) ON CONFLICT(namespace) DO UPDATE SET version = excluded.version",
+        qualify(table, table.name)
+    )
+}
+
+pub(crate) fn delete_latest_version(table: &TrackingTable<'_>) -> String {
+    format!(
+        "DELETE FROM {} WHERE namespace = ?",
+        qualify(table, table.name)
+    )
+}
+
+#[cfg(any(feature = "rusqlite", feature = "sqlx-sqlite"))]
+pub(crate) fn select_applied(table: &TrackingTable<'_>) -> String {
+    format!(
+        "SELECT version, name, out_of_order, checksum FROM {} WHERE namespace = ? ORDER BY version",
+        qualify(table, &table.history_name())
+    )
+}
+
+pub(crate) fn insert_applied(table: &TrackingTable<'_>) -> String {
+    format!(
+        "INSERT INTO {}(namespace, version, name, out_of_order, checksum) VALUES(?, ?, ?, ?, ?)",
+        qualify(table, &table.history_name())
+    )
+}
+
+pub(crate) fn delete_applied(table: &TrackingTable<'_>) -> String {
+    format!(
+        "DELETE FROM {} WHERE namespace = ? AND version = ?",
+        qualify(table, &table.history_name())
+    )
+}
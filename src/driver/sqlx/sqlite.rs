@@ -1,23 +1,29 @@
-use crate::{Driver, Transaction};
+use crate::driver::sqlite::{self, TrackingConnection};
+use crate::{AppliedMigration, Driver, TrackingTable, Transaction};
 
 use futures::executor;
 use sqlx::{
     sqlite::{Sqlite, SqliteConnection},
-    Connection, Error,
+    Connection, Row,
 };
-use std::array::TryFromSliceError;
-use std::convert::TryInto;
-
-// SQL definition for the backing table
-const MIGRATION_BACKING_DEF: &'static str = r#"
-CREATE TABLE IF NOT EXISTS nomad_migrations (
-    namespace   text not null primary key,
-    version     blob not null
-) WITHOUT ROWID;
-"#;
-
-fn ensure_migration_table(conn: &mut SqliteConnection) -> Result<(), sqlx::Error> {
-    executor::block_on(sqlx::query(MIGRATION_BACKING_DEF).execute(conn)).and(Ok(()))
+
+impl TrackingConnection for SqliteConnection {
+    type Error = sqlx::Error;
+
+    fn execute(&mut self, sql: &str) -> Result<(), sqlx::Error> {
+        executor::block_on(sqlx::query(sql).execute(self)).and(Ok(()))
+    }
+
+    fn query_integer(&mut self, sql: &str) -> Result<Option<i64>, sqlx::Error> {
+        executor::block_on(sqlx::query_scalar(sql).fetch_optional(self))
+    }
+}
+
+fn ensure_migration_table(
+    conn: &mut SqliteConnection,
+    table: &TrackingTable<'_>,
+) -> Result<(), sqlx::Error> {
+    sqlite::ensure_tracking_tables(conn, table)
 }
 
 impl<'a> Transaction<'a, SqliteConnection> for sqlx::Transaction<'a, Sqlite> {
@@ -31,21 +37,71 @@ impl<'a> Transaction<'a, SqliteConnection> for sqlx::Transaction<'a, Sqlite> {
 
     fn push_latest_version(
         &mut self,
+        table: &TrackingTable<'_>,
         namespace: &str,
         version: u64,
     ) -> Result<(), <SqliteConnection as Driver<'a>>::Error> {
-        ensure_migration_table(self)?;
+        ensure_migration_table(self, table)?;
+
+        executor::block_on(
+            sqlx::query(&sqlite::upsert_latest_version(table))
+                .bind(namespace)
+                .bind(version as i64)
+                .execute(self),
+        )
+        .and(Ok(()))
+    }
+
+    fn record_applied(
+        &mut self,
+        table: &TrackingTable<'_>,
+        namespace: &str,
+        migration: &AppliedMigration,
+    ) -> Result<(), <SqliteConnection as Driver<'a>>::Error> {
+        ensure_migration_table(self, table)?;
 
-        let version_bytes = &version.to_le_bytes()[..];
+        executor::block_on(
+            sqlx::query(&sqlite::insert_applied(table))
+                .bind(namespace)
+                .bind(migration.version as i64)
+                .bind(migration.name.as_str())
+                .bind(migration.out_of_order)
+                .bind(migration.checksum.map(i64::from))
+                .execute(self),
+        )
+        .and(Ok(()))
+    }
+
+    fn remove_applied(
+        &mut self,
+        table: &TrackingTable<'_>,
+        namespace: &str,
+        version: u64,
+        latest_version: Option<u64>,
+    ) -> Result<(), <SqliteConnection as Driver<'a>>::Error> {
+        ensure_migration_table(self, table)?;
 
         executor::block_on(
-            sqlx::query(
-                "INSERT INTO nomad_migrations(namespace, version) VALUES(?, ?) ON CONFLICT(namespace) DO UPDATE SET version = excluded.version"
-            )
-            .bind(namespace)
-            .bind(version_bytes)
-            .execute(self)
-        ).and(Ok(()))
+            sqlx::query(&sqlite::delete_applied(table))
+                .bind(namespace)
+                .bind(version as i64)
+                .execute(&mut *self),
+        )?;
+
+        match latest_version {
+            Some(latest_version) => executor::block_on(
+                sqlx::query(&sqlite::upsert_latest_version(table))
+                    .bind(namespace)
+                    .bind(latest_version as i64)
+                    .execute(self),
+            ),
+            None => executor::block_on(
+                sqlx::query(&sqlite::delete_latest_version(table))
+                    .bind(namespace)
+                    .execute(self),
+            ),
+        }
+        .and(Ok(()))
     }
 }
 
@@ -57,27 +113,55 @@ impl<'a> Driver<'a> for SqliteConnection {
         executor::block_on(Connection::begin(self))
     }
 
-    fn latest_version(&mut self, namespace: &str) -> Result<Option<u64>, Self::Error> {
-        fn map_conv_err(err: TryFromSliceError) -> Error {
-            Error::ColumnDecode {
-                index: "version".to_owned(),
-                source: Box::new(err),
-            }
-        }
-
-        ensure_migration_table(self)?;
+    fn latest_version(
+        &mut self,
+        table: &TrackingTable<'_>,
+        namespace: &str,
+    ) -> Result<Option<u64>, Self::Error> {
+        ensure_migration_table(self, table)?;
 
-        let version: Option<Vec<u8>> = futures::executor::block_on(
-            sqlx::query_scalar("SELECT version FROM nomad_migrations WHERE namespace = ?")
+        let version: Option<i64> = executor::block_on(
+            sqlx::query_scalar(&sqlite::select_latest_version(table))
                 .bind(namespace)
                 .fetch_optional(self),
         )?;
 
-        Ok(match version {
-            Some(vec) => Some(u64::from_le_bytes(
-                vec[..].try_into().map_err(map_conv_err)?,
-            )),
-            None => None,
-        })
+        Ok(version.map(|version| version as u64))
+    }
+
+    fn applied_migrations(
+        &mut self,
+        table: &TrackingTable<'_>,
+        namespace: &str,
+    ) -> Result<Vec<AppliedMigration>, Self::Error> {
+        ensure_migration_table(self, table)?;
+
+        let rows: Vec<(i64, String, bool, Option<i64>)> = executor::block_on(
+            sqlx::query_as(&sqlite::select_applied(table))
+                .bind(namespace)
+                .fetch_all(self),
+        )?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(version, name, out_of_order, checksum)| AppliedMigration {
+                version: version as u64,
+                name,
+                out_of_order,
+                checksum: checksum.map(|checksum| checksum as u32),
+            })
+            .collect())
+    }
+
+    fn query_rows(&mut self, sql: &str) -> Result<Vec<Vec<Option<String>>>, Self::Error> {
+        let rows = executor::block_on(sqlx::query(sql).fetch_all(self))?;
+
+        rows.iter()
+            .map(|row| {
+                (0..row.len())
+                    .map(|index| row.try_get::<Option<String>, _>(index))
+                    .collect()
+            })
+            .collect()
     }
 }
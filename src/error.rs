@@ -4,6 +4,49 @@ use std::fmt::{Debug, Display, Formatter};
 #[derive(Debug)]
 pub enum MigrationError<E: Error> {
     TimeTravelError,
+    DuplicateVersion {
+        version: u64,
+        first: String,
+        second: String,
+    },
+    UnappliedVersion {
+        version: u64,
+        name: String,
+    },
+    ExecutionError {
+        version: u64,
+        name: String,
+        source: E,
+    },
+    UnknownVersion {
+        version: u64,
+    },
+    AlreadyTracked {
+        namespace: String,
+    },
+    InvalidImport {
+        table: &'static str,
+        reason: String,
+    },
+    BehindBaseline {
+        latest: u64,
+        baseline: u64,
+    },
+    IrreversibleMigration {
+        version: u64,
+        name: String,
+    },
+    UnknownPlaceholder {
+        version: u64,
+        name: String,
+        placeholder: String,
+    },
+    ChecksumMismatch {
+        version: u64,
+        name: String,
+        applied: u32,
+        current: u32,
+    },
     DriverError(E),
 }
 
@@ -14,12 +57,94 @@ impl<E: Error> Display for MigrationError<E> {
                 f,
                 "Latest migrated version exceeds latest known version - possible downgrade"
             ),
+            Self::DuplicateVersion {
+                version,
+                first,
+                second,
+            } => write!(
+                f,
+                "Migration version {} is defined more than once ({} and {})",
+                version, first, second
+            ),
+            Self::UnappliedVersion { version, name } => write!(
+                f,
+                "Migration {} {} was never applied but is older than the latest migrated version",
+                version, name
+            ),
+            Self::ExecutionError {
+                version,
+                name,
+                source,
+            } => write!(f, "Migration {} {} failed: {}", version, name, source),
+            Self::UnknownVersion { version } => write!(
+                f,
+                "Version {} has been applied but is not a known migration",
+                version
+            ),
+            Self::AlreadyTracked { namespace } => write!(
+                f,
+                "Namespace \"{}\" already has migrations tracked by nomad",
+                namespace
+            ),
+            Self::InvalidImport { table, reason } => {
+                write!(f, "Unable to import from {}: {}", table, reason)
+            }
+            Self::BehindBaseline { latest, baseline } => write!(
+                f,
+                "Latest migrated version {} is older than the baseline {} - apply the squashed migrations first",
+                latest, baseline
+            ),
+            Self::IrreversibleMigration { version, name } => write!(
+                f,
+                "Migration {} {} has no down migration, so can't be rolled back",
+                version, name
+            ),
+            Self::UnknownPlaceholder {
+                version,
+                name,
+                placeholder,
+            } => write!(
+                f,
+                "Migration {} {} uses the placeholder ${{{}}}, which has no value",
+                version, name, placeholder
+            ),
+            Self::ChecksumMismatch {
+                version,
+                name,
+                applied,
+                current,
+            } => write!(
+                f,
+                "Migration {} {} has changed since it was applied (checksum {:08x}, now {:08x})",
+                version, name, applied, current
+            ),
             Self::DriverError(err) => Display::fmt(err, f),
         }
     }
 }
 
-impl<E: Error> Error for MigrationError<E> {}
+impl<E: Error + 'static> Error for MigrationError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::ExecutionError { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+// A namespace registered with a MigrationRegistry more than once
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateNamespace {
+    pub namespace: String,
+}
+
+impl Display for DuplicateNamespace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Namespace \"{}\" is already registered", self.namespace)
+    }
+}
+
+impl Error for DuplicateNamespace {}
 
 impl<E: Error> From<E> for MigrationError<E> {
     fn from(err: E) -> Self {
@@ -0,0 +1,138 @@
+use crate::{
+    check_unique, AppliedMigration, Driver, Migration, MigrationError, MigrationRunner,
+    OrderedMigrations, Transaction,
+};
+
+// A migration tool whose tracking table can be imported into nomad
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ImportSource {
+    // sqlx-cli's _sqlx_migrations table
+    Sqlx,
+    // refinery's refinery_schema_history table
+    Refinery,
+    // diesel's __diesel_schema_migrations table
+    Diesel,
+}
+
+impl ImportSource {
+    pub fn table_name(&self) -> &'static str {
+        match self {
+            Self::Sqlx => "_sqlx_migrations",
+            Self::Refinery => "refinery_schema_history",
+            Self::Diesel => "__diesel_schema_migrations",
+        }
+    }
+
+    // Query returning each applied version, and whether it completed successfully
+    fn query(&self) -> String {
+        match self {
+            Self::Sqlx => format!(
+                "SELECT CAST(version AS TEXT), CAST(success AS TEXT) FROM {} ORDER BY version",
+                self.table_name()
+            ),
+            Self::Refinery | Self::Diesel => format!(
+                "SELECT CAST(version AS TEXT), '1' FROM {} ORDER BY version",
+                self.table_name()
+            ),
+        }
+    }
+}
+
+fn parse_version(source: ImportSource, version: &str) -> Option<u64> {
+    match source {
+        // Diesel versions are strings, normally a timestamp with or without separators
+        ImportSource::Diesel => version.replace('-', "").parse().ok(),
+        _ => version.parse().ok(),
+    }
+}
+
+fn parse_success(value: Option<&str>) -> bool {
+    match value {
+        Some(value) => matches!(value.to_lowercase().as_str(), "1" | "t" | "true"),
+        None => false,
+    }
+}
+
+impl<'d, 'n, D: Driver<'d>> MigrationRunner<'d, 'n, D> {
+    // Adopt the state of another migration tool's tracking table.
+    // Every version applied by the other tool must be one of the given migrations,
+    // and the namespace must not already be tracked by nomad.
+    // Returns the migrations recorded as applied.
+    pub fn import<'a, T: AsRef<[Migration<'a>]>, C: Into<OrderedMigrations<'a, T>>>(
+        self,
+        source: ImportSource,
+        migrations: C,
+    ) -> Result<Vec<AppliedMigration>, MigrationError<D::Error>> {
+        let migrations = migrations.into();
+        let migrations = self.render(migrations.as_ref())?;
+        check_unique(&migrations)?;
+
+        if self
+            .driver
+            .latest_version(&self.tracking_table, self.namespace)?
+            .is_some()
+        {
+            return Err(MigrationError::AlreadyTracked {
+                namespace: self.namespace.to_owned(),
+            });
+        }
+
+        let mut applied = Vec::new();
+        for row in self.driver.query_rows(&source.query())? {
+            let version = row.first().and_then(|version| version.as_deref());
+            let version = version
+                .and_then(|version| parse_version(source, version))
+                .ok_or_else(|| MigrationError::InvalidImport {
+                    table: source.table_name(),
+                    reason: format!("{:?} is not a valid version", version),
+                })?;
+
+            if !parse_success(row.get(1).and_then(|success| success.as_deref())) {
+                return Err(MigrationError::InvalidImport {
+                    table: source.table_name(),
+                    reason: format!("migration {} did not complete successfully", version),
+                });
+            }
+
+            let migration = migrations
+                .binary_search_by_key(&version, |migration| migration.version)
+                .map(|index| &migrations[index])
+                .map_err(|_| MigrationError::UnknownVersion { version })?;
+
+            applied.push(AppliedMigration::assumed(migration));
+        }
+
+        applied.sort_by_key(|migration| migration.version);
+
+        // Known migrations older than the latest imported version must have been applied too
+        if let Some(latest) = applied.last().map(|migration| migration.version) {
+            let gap = migrations.iter().find(|migration| {
+                migration.version < latest
+                    && !applied
+                        .iter()
+                        .any(|applied| applied.version == migration.version)
+            });
+
+            if let Some(migration) = gap {
+                return Err(MigrationError::UnappliedVersion {
+                    version: migration.version,
+                    name: migration.name.to_string(),
+                });
+            }
+        }
+
+        let mut txn = self.driver.begin()?;
+
+        for migration in &applied {
+            txn.record_applied(&self.tracking_table, self.namespace, migration)?;
+        }
+
+        if let Some(latest) = applied.last().map(|migration| migration.version) {
+            txn.push_latest_version(&self.tracking_table, self.namespace, latest)?;
+        }
+
+        txn.commit()?;
+
+        Ok(applied)
+    }
+}
@@ -1,6 +1,8 @@
 extern crate nomad_macro;
 pub use nomad_macro::*;
 
+extern crate nomad_files;
+
 #[cfg(feature = "rusqlite")]
 extern crate rusqlite;
 
@@ -9,71 +11,302 @@ extern crate futures;
 #[cfg(feature = "sqlx")]
 extern crate sqlx;
 
+pub mod build;
 mod driver;
 mod error;
+mod import;
+mod load;
 mod migration;
 mod ordered;
+mod placeholder;
+mod registry;
+mod rollback;
+pub mod scaffold;
+mod schema;
+pub mod script;
+mod squash;
+pub mod testing;
+mod timestamp;
+mod tracking;
+mod verify;
 
 pub use driver::{Driver, Transaction};
 pub use error::*;
+pub use import::ImportSource;
+pub use load::{load_dir, load_dir_with, LoadedMigrations};
 pub use migration::*;
+pub use nomad_files::{Convention, FileError, ReadOptions};
+pub use registry::{MigrationRegistry, MigrationReport};
+pub use squash::{squash, Baseline, BASELINE_NAME};
+pub use timestamp::Timestamp;
+pub use tracking::{TrackingTable, DEFAULT_TRACKING_TABLE};
+pub use verify::SchemaDifference;
+
+use std::collections::HashMap;
 
 pub type OrderedMigrations<'a, T> = ordered::OrderedArray<Migration<'a>, T>;
 pub const DEFAULT_NAMESPACE: &'static str = "nomad";
 
+// How to treat a known migration that is older than the latest applied version, but was never applied
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum OrderPolicy {
+    // Refuse to migrate, returning an UnappliedVersion error
+    #[default]
+    Strict,
+    // Apply the migration late, recording that it was applied out of order
+    OutOfOrder,
+}
+
 pub struct MigrationRunner<'d, 'n, D: Driver<'d>> {
     pub driver: &'d mut D,
     pub namespace: &'n str,
+    pub order_policy: OrderPolicy,
+    pub tracking_table: TrackingTable<'n>,
+    // Values for ${name} placeholders in migration SQL, or None to run the SQL as written
+    pub placeholders: Option<HashMap<String, String>>,
+    // Whether applied migrations must still match the checksum recorded when they were run
+    pub verify_checksums: bool,
 }
 
 impl<'d, D: Driver<'d>> MigrationRunner<'d, 'static, D> {
     pub fn new(driver: &'d mut D) -> Self {
-        MigrationRunner {
-            driver,
-            namespace: DEFAULT_NAMESPACE,
-        }
+        Self::with_namespace(driver, DEFAULT_NAMESPACE)
     }
 }
 
 impl<'d, 'n, D: Driver<'d>> MigrationRunner<'d, 'n, D> {
     pub fn with_namespace(driver: &'d mut D, namespace: &'n str) -> Self {
-        MigrationRunner { driver, namespace }
+        MigrationRunner {
+            driver,
+            namespace,
+            order_policy: OrderPolicy::default(),
+            tracking_table: TrackingTable::default(),
+            placeholders: None,
+            verify_checksums: false,
+        }
+    }
+
+    pub fn with_order_policy(mut self, order_policy: OrderPolicy) -> Self {
+        self.order_policy = order_policy;
+        self
+    }
+
+    pub fn with_tracking_table(mut self, tracking_table: TrackingTable<'n>) -> Self {
+        self.tracking_table = tracking_table;
+        self
+    }
+
+    // Substitute ${name} placeholders in the up and down SQL of every migration, such as ${prefix}.
+    // Once opted in, even with no values, a placeholder without a value is an UnknownPlaceholder error,
+    // and $${ writes a literal ${. Without placeholders the SQL runs as written.
+    // The rendered SQL is what gets run and checksummed, so changing a value changes the checksum
+    // of every applied migration that uses it.
+    pub fn with_placeholders<I, K, V>(mut self, placeholders: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.placeholders.get_or_insert_with(HashMap::new).extend(
+            placeholders
+                .into_iter()
+                .map(|(name, value)| (name.into(), value.into())),
+        );
+        self
+    }
+
+    // Fail with a ChecksumMismatch when an applied migration's SQL has changed since nomad ran it.
+    // Off by default, so editing an applied migration or a placeholder value doesn't block migrating.
+    pub fn with_verify_checksums(mut self, verify_checksums: bool) -> Self {
+        self.verify_checksums = verify_checksums;
+        self
+    }
+
+    // The migrations as they will be run, with their placeholders substituted
+    pub(crate) fn render<'a>(
+        &self,
+        migrations: &[Migration<'a>],
+    ) -> Result<Vec<Migration<'a>>, MigrationError<D::Error>> {
+        placeholder::render(migrations, self.placeholders.as_ref())
     }
 
     pub fn migrate<'a, T: AsRef<[Migration<'a>]>, C: Into<OrderedMigrations<'a, T>>>(
         self,
         migrations: C,
     ) -> Result<Option<u64>, MigrationError<D::Error>> {
-        let latest_version = self.driver.latest_version(self.namespace)?;
+        let migrations = migrations.into();
+        let migrations = self.render(migrations.as_ref())?;
+        let state = NamespaceState::read(self.driver, &self.tracking_table, self.namespace)?;
 
         let mut txn = self.driver.begin()?;
+        let applied = migrate_namespace::<D>(
+            &mut txn,
+            &self.tracking_table,
+            self.namespace,
+            &state,
+            self.order_policy,
+            self.verify_checksums,
+            &migrations,
+        )?;
+        txn.commit()?;
+
+        Ok(applied.last().map(|migration| migration.version))
+    }
+}
+
+// The tracked state of a namespace, read before a migration transaction begins
+pub(crate) struct NamespaceState {
+    latest_version: Option<u64>,
+    applied_migrations: Vec<AppliedMigration>,
+}
 
-        let mut last_executed = None;
-        let mut latest_observed = None;
-        for migration in &migrations.into() {
-            latest_observed = Some(migration.version);
+impl NamespaceState {
+    pub(crate) fn read<'d, D: Driver<'d>>(
+        driver: &mut D,
+        table: &TrackingTable<'_>,
+        namespace: &str,
+    ) -> Result<Self, D::Error> {
+        Ok(NamespaceState {
+            latest_version: driver.latest_version(table, namespace)?,
+            applied_migrations: driver.applied_migrations(table, namespace)?,
+        })
+    }
+
+    // Namespaces migrated before per-version history was tracked only know their latest version
+    fn is_legacy(&self) -> bool {
+        self.applied_migrations.is_empty() && self.latest_version.is_some()
+    }
 
-            match latest_version {
-                Some(version) if migration.version <= version => continue,
+    fn applied(&self, version: u64) -> Option<&AppliedMigration> {
+        self.applied_migrations
+            .binary_search_by_key(&version, |applied| applied.version)
+            .ok()
+            .map(|index| &self.applied_migrations[index])
+    }
+
+    fn is_applied(&self, version: u64) -> bool {
+        self.applied(version).is_some()
+    }
+}
+
+// Migration sets converted with From are sorted but may still hold duplicate versions,
+// which every operation on a set rejects before touching the database
+pub(crate) fn check_unique<E: std::error::Error>(
+    migrations: &[Migration<'_>],
+) -> Result<(), MigrationError<E>> {
+    for pair in migrations.windows(2) {
+        if pair[0].version == pair[1].version {
+            return Err(MigrationError::DuplicateVersion {
+                version: pair[0].version,
+                first: pair[0].name.to_string(),
+                second: pair[1].name.to_string(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+// Apply any pending migrations for a namespace within an existing transaction.
+// Returns the migrations that were applied, in the order they were applied.
+pub(crate) fn migrate_namespace<'d, D: Driver<'d>>(
+    txn: &mut D::Transaction,
+    table: &TrackingTable<'_>,
+    namespace: &str,
+    state: &NamespaceState,
+    order_policy: OrderPolicy,
+    verify_checksums: bool,
+    migrations: &[Migration<'_>],
+) -> Result<Vec<AppliedMigration>, MigrationError<D::Error>> {
+    fn execute<'d, D: Driver<'d>>(
+        txn: &mut D::Transaction,
+        migration: &Migration<'_>,
+    ) -> Result<(), MigrationError<D::Error>> {
+        txn.execute_sql(&migration.sql)
+            .map_err(|source| MigrationError::ExecutionError {
+                version: migration.version,
+                name: migration.name.to_string(),
+                source,
+            })
+    }
+
+    check_unique(migrations)?;
+
+    let mut applied = Vec::new();
+    for migration in squash::since_baseline(migrations) {
+        if migration.is_baseline() {
+            match state.latest_version {
+                Some(latest) if latest < migration.version => {
+                    return Err(MigrationError::BehindBaseline {
+                        latest,
+                        baseline: migration.version,
+                    });
+                }
+                // Existing databases already have the squashed schema, though legacy ones still backfill it
+                Some(_) if !state.is_legacy() => continue,
                 _ => {}
             }
+        }
 
-            txn.execute_sql(migration.sql)?;
-            txn.push_latest_version(self.namespace, migration.version)?;
+        match state.latest_version {
+            Some(version) if migration.version <= version => {
+                if state.is_legacy() {
+                    // Everything up to the latest version was applied, so backfill the history
+                    txn.record_applied(table, namespace, &AppliedMigration::assumed(migration))?;
+                    continue;
+                }
 
-            last_executed = Some(migration.version);
-        }
+                if let Some(record) = state.applied(migration.version) {
+                    // Migrations nomad never ran have no checksum to compare
+                    let current = migration.checksum();
+                    match record.checksum {
+                        Some(applied) if verify_checksums && applied != current => {
+                            return Err(MigrationError::ChecksumMismatch {
+                                version: migration.version,
+                                name: migration.name.to_string(),
+                                applied,
+                                current,
+                            });
+                        }
+                        _ => continue,
+                    }
+                }
 
-        if let Some(latest_observed) = latest_observed {
-            if let Some(latest_version) = latest_version {
-                if latest_version > latest_observed {
-                    return Err(MigrationError::TimeTravelError);
+                if order_policy == OrderPolicy::Strict {
+                    return Err(MigrationError::UnappliedVersion {
+                        version: migration.version,
+                        name: migration.name.to_string(),
+                    });
                 }
+
+                // The latest version stays put, only the history records the late migration
+                let record = AppliedMigration::new(migration, true);
+
+                execute::<D>(txn, migration)?;
+                txn.record_applied(table, namespace, &record)?;
+
+                applied.push(record);
+                continue;
             }
+            _ => {}
         }
 
-        txn.commit()?;
+        let record = AppliedMigration::new(migration, false);
+
+        execute::<D>(txn, migration)?;
+        txn.push_latest_version(table, namespace, migration.version)?;
+        txn.record_applied(table, namespace, &record)?;
 
-        Ok(last_executed)
+        applied.push(record);
     }
+
+    if let Some(latest_observed) = squash::since_baseline(migrations).last() {
+        if let Some(latest_version) = state.latest_version {
+            if latest_version > latest_observed.version {
+                return Err(MigrationError::TimeTravelError);
+            }
+        }
+    }
+
+    Ok(applied)
 }
@@ -0,0 +1,39 @@
+// Loading migrations from disk at runtime, for SQL shipped alongside a binary instead of embedded in it
+use crate::{Migration, OrderedMigrations};
+use nomad_files::{FileError, ReadOptions};
+use std::fs;
+use std::path::Path;
+
+// Migrations loaded at runtime own their names and SQL
+pub type LoadedMigrations = OrderedMigrations<'static, Vec<Migration<'static>>>;
+
+// Load every migration in a directory, using the same naming rules as nomad_migrations!
+pub fn load_dir<P: AsRef<Path>>(path: P) -> Result<LoadedMigrations, FileError> {
+    load_dir_with(path, &ReadOptions::default())
+}
+
+// Load every migration in a directory, with the same options nomad_migrations! accepts
+pub fn load_dir_with<P: AsRef<Path>>(
+    path: P,
+    options: &ReadOptions,
+) -> Result<LoadedMigrations, FileError> {
+    let read = |path: &Path| {
+        fs::read_to_string(path).map_err(|source| FileError::Io {
+            path: path.to_owned(),
+            source,
+        })
+    };
+
+    let mut migrations = Vec::new();
+    for file in options.read_dir(path.as_ref())? {
+        let mut migration = Migration::new(file.version, file.name, read(&file.path)?);
+        if let Some(down_path) = &file.down_path {
+            migration = migration.with_down(read(down_path)?);
+        }
+
+        migrations.push(migration);
+    }
+
+    // read_dir sorts the migrations, and rejects duplicate versions
+    Ok(unsafe { OrderedMigrations::new_unsafe(migrations) })
+}
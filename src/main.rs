@@ -0,0 +1,465 @@
+// The nomad command line tool, migrating SQLite databases from a directory of migration files
+extern crate nomad;
+extern crate rusqlite;
+
+use nomad::scaffold::{NewMigration, VersionScheme};
+use nomad::script::{Dialect, Script};
+use nomad::{
+    load_dir_with, squash, AppliedMigration, Convention, Driver, Migration, MigrationRegistry,
+    MigrationRunner, ReadOptions, TrackingTable, DEFAULT_NAMESPACE, DEFAULT_TRACKING_TABLE,
+};
+use rusqlite::Connection;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+const USAGE: &str = "Usage: nomad <command> [options]
+
+Commands:
+    status              List every migration, and whether it has been applied
+    migrate             Apply pending migrations
+    rollback            Revert the latest migration, or every migration after --to
+    baseline <version>  Squash every migration up to a version into a baseline migration
+    plan                Print the SQL script that migrate would run
+    verify              Check the database's schema against its applied migrations
+    new <name>          Create the next migration file
+
+Options:
+    -d, --database <url>      SQLite database, as a path or sqlite:// URL. Defaults to $DATABASE_URL
+    -m, --migrations <dir>    Directory of migrations. Defaults to ./migrations
+    -n, --namespace <name>    Namespace to migrate. Defaults to nomad
+    -c, --convention <name>   Name migrations like nomad, flyway, golang-migrate or diesel. Defaults to nomad
+        --to <version>        Version to migrate, plan or roll back to
+        --tracking-table <[schema.]name>
+                              Table tracking applied migrations. Defaults to nomad_migrations
+    -p, --placeholder <name=value>
+                              Replace ${name} in migrations with value. May be given more than once
+
+Options for migrate:
+        --verify-checksums    Fail if an applied migration has changed since it was run
+
+Options for new:
+        --timestamp           Version the migration with the current time instead of the next number
+        --down                Also create a down migration
+        --header <text>       Start each file with this text, replacing {version}, {name} and {direction}";
+
+struct Options {
+    command: String,
+    args: Vec<String>,
+    database: Option<String>,
+    migrations: PathBuf,
+    namespace: String,
+    convention: Convention,
+    to: Option<u64>,
+    tracking_schema: Option<String>,
+    tracking_table: String,
+    placeholders: Vec<(String, String)>,
+    verify_checksums: bool,
+    timestamp: bool,
+    down: bool,
+    header: Option<String>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        command: args.next().ok_or("Missing command")?,
+        args: Vec::new(),
+        database: env::var("DATABASE_URL").ok(),
+        migrations: PathBuf::from("migrations"),
+        namespace: DEFAULT_NAMESPACE.to_owned(),
+        convention: Convention::Nomad,
+        to: None,
+        tracking_schema: None,
+        tracking_table: DEFAULT_TRACKING_TABLE.to_owned(),
+        placeholders: Vec::new(),
+        verify_checksums: false,
+        timestamp: false,
+        down: false,
+        header: None,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("Missing value for {}", arg))
+        };
+
+        match arg.as_str() {
+            "-d" | "--database" => options.database = Some(value()?),
+            "-m" | "--migrations" => options.migrations = PathBuf::from(value()?),
+            "-n" | "--namespace" => options.namespace = value()?,
+            "-c" | "--convention" => {
+                let convention = value()?;
+                options.convention = Convention::from_name(&convention)
+                    .ok_or_else(|| format!("Unknown convention {}", convention))?;
+            }
+            "--to" => {
+                let to = value()?;
+                options.to = Some(
+                    to.parse()
+                        .map_err(|_| format!("\"{}\" is not a valid version", to))?,
+                );
+            }
+            "--tracking-table" => {
+                let table = value()?;
+                match table.split_once('.') {
+                    Some((schema, name)) => {
+                        options.tracking_schema = Some(schema.to_owned());
+                        options.tracking_table = name.to_owned();
+                    }
+                    None => options.tracking_table = table,
+                }
+            }
+            "-p" | "--placeholder" => {
+                let placeholder = value()?;
+                let (name, value) = placeholder
+                    .split_once('=')
+                    .ok_or_else(|| format!("\"{}\" is not a name=value pair", placeholder))?;
+                options
+                    .placeholders
+                    .push((name.to_owned(), value.to_owned()));
+            }
+            "--verify-checksums" => options.verify_checksums = true,
+            "--timestamp" => options.timestamp = true,
+            "--down" => options.down = true,
+            "--header" => options.header = Some(value()?),
+            _ if arg.starts_with('-') => return Err(format!("Unknown option {}", arg)),
+            _ => options.args.push(arg),
+        }
+    }
+
+    Ok(options)
+}
+
+fn read_options(options: &Options) -> ReadOptions {
+    ReadOptions::new().with_convention(options.convention)
+}
+
+fn tracking_table(options: &Options) -> TrackingTable<'_> {
+    let table = TrackingTable::new(&options.tracking_table);
+
+    match &options.tracking_schema {
+        Some(schema) => table.with_schema(schema),
+        None => table,
+    }
+}
+
+// A runner for the chosen namespace and tracking table.
+// Placeholders are only substituted when some are given, so SQL containing ${ otherwise runs as written.
+fn runner<'c>(
+    conn: &'c mut Connection,
+    options: &'c Options,
+) -> MigrationRunner<'c, 'c, Connection> {
+    let runner = MigrationRunner::with_namespace(conn, &options.namespace)
+        .with_tracking_table(tracking_table(options));
+
+    if options.placeholders.is_empty() {
+        runner
+    } else {
+        runner.with_placeholders(options.placeholders.iter().cloned())
+    }
+}
+
+// Commands writing migration files only know how to name them nomad's way
+fn require_nomad_convention(options: &Options) -> Result<(), Box<dyn Error>> {
+    if options.convention != Convention::Nomad {
+        return Err(format!("{} only supports the nomad convention", options.command).into());
+    }
+
+    Ok(())
+}
+
+fn open_database(options: &Options) -> Result<Connection, Box<dyn Error>> {
+    let database = options
+        .database
+        .as_deref()
+        .ok_or("No database given, pass --database or set DATABASE_URL")?;
+
+    let path = database
+        .strip_prefix("sqlite://")
+        .or_else(|| database.strip_prefix("sqlite:"))
+        .unwrap_or(database);
+
+    Ok(Connection::open(path)?)
+}
+
+// The applied migrations of a namespace, including those of namespaces without history
+fn applied_migrations(
+    conn: &mut Connection,
+    options: &Options,
+    migrations: &[Migration<'_>],
+) -> Result<Vec<AppliedMigration>, Box<dyn Error>> {
+    let table = tracking_table(options);
+    let latest = conn.latest_version(&table, &options.namespace)?;
+    let applied = conn.applied_migrations(&table, &options.namespace)?;
+
+    if !applied.is_empty() || latest.is_none() {
+        return Ok(applied);
+    }
+
+    Ok(migrations
+        .iter()
+        .filter(|migration| Some(migration.version) <= latest)
+        .map(|migration| AppliedMigration {
+            version: migration.version,
+            name: migration.name.to_string(),
+            out_of_order: false,
+            checksum: None,
+        })
+        .collect())
+}
+
+fn status(options: &Options, migrations: &[Migration<'_>]) -> Result<i32, Box<dyn Error>> {
+    let mut conn = open_database(options)?;
+    let applied = applied_migrations(&mut conn, options, migrations)?;
+
+    let mut rows = Vec::new();
+    for migration in migrations {
+        let status = match applied
+            .iter()
+            .find(|applied| applied.version == migration.version)
+        {
+            Some(applied) if applied.out_of_order => "applied out of order",
+            Some(_) => "applied",
+            None => "pending",
+        };
+
+        rows.push((migration.version, migration.name.to_string(), status));
+    }
+
+    for applied in &applied {
+        if !migrations
+            .iter()
+            .any(|migration| migration.version == applied.version)
+        {
+            rows.push((
+                applied.version,
+                applied.name.clone(),
+                "applied, missing file",
+            ));
+        }
+    }
+
+    rows.sort_by_key(|(version, _, _)| *version);
+
+    for (version, name, status) in rows {
+        println!("{:<22} {} {}", status, version, name);
+    }
+
+    Ok(0)
+}
+
+fn migrate(options: &Options, migrations: &[Migration<'_>]) -> Result<i32, Box<dyn Error>> {
+    let mut conn = open_database(options)?;
+    let migrations = migrations
+        .iter()
+        .filter(|migration| options.to.is_none() || Some(migration.version) <= options.to)
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let mut registry = MigrationRegistry::new();
+    registry
+        .with_tracking_table(tracking_table(options))
+        .with_verify_checksums(options.verify_checksums)
+        .register(&options.namespace, migrations)?;
+    if !options.placeholders.is_empty() {
+        registry.with_placeholders(options.placeholders.iter().cloned());
+    }
+
+    let applied = registry
+        .migrate_all(&mut conn)?
+        .into_iter()
+        .flat_map(|report| report.applied)
+        .collect::<Vec<_>>();
+
+    if applied.is_empty() {
+        println!("No pending migrations");
+    }
+
+    for migration in applied {
+        println!("Applied {}", migration);
+    }
+
+    Ok(0)
+}
+
+fn rollback(options: &Options, migrations: &[Migration<'_>]) -> Result<i32, Box<dyn Error>> {
+    let mut conn = open_database(options)?;
+    let applied = applied_migrations(&mut conn, options, migrations)?;
+
+    if applied.is_empty() {
+        println!("No migrations to roll back");
+        return Ok(0);
+    }
+
+    // Without a target, only the latest migration is reverted
+    let to = match options.to {
+        Some(to) => Some(to),
+        None => applied
+            .iter()
+            .rev()
+            .nth(1)
+            .map(|migration| migration.version),
+    };
+
+    let reverted = runner(&mut conn, options).rollback(migrations.to_vec(), to)?;
+
+    for migration in reverted {
+        println!("Reverted {}", migration);
+    }
+
+    Ok(0)
+}
+
+fn baseline(options: &Options, migrations: &[Migration<'_>]) -> Result<i32, Box<dyn Error>> {
+    require_nomad_convention(options)?;
+
+    let through = match options.args.first() {
+        Some(version) => version
+            .parse()
+            .map_err(|_| format!("\"{}\" is not a valid version", version))?,
+        None => return Err("Missing the version to squash through".into()),
+    };
+
+    let baseline = squash(
+        &mut Connection::open_in_memory()?,
+        migrations.to_vec(),
+        through,
+    )?;
+
+    // Listed before the baseline is written, as it shares a version with one of the files it replaces
+    let files = read_options(options).read_dir(&options.migrations)?;
+
+    // Written before anything is removed, so a failure never leaves the squashed migrations without a replacement
+    let baseline_path = options.migrations.join(baseline.file_name());
+    fs::write(&baseline_path, &baseline.sql)?;
+
+    // The baseline replaces every migration it squashes, including the one sharing its version
+    for file in files {
+        // Squashing through an existing baseline overwrites it in place
+        if file.version > through || file.path == baseline_path {
+            continue;
+        }
+
+        // Diesel's layout keeps each migration in its own directory
+        let path = match file.path.parent() {
+            Some(dir) if file.path.ends_with("up.sql") => {
+                fs::remove_dir_all(dir)?;
+                dir.to_owned()
+            }
+            _ => {
+                fs::remove_file(&file.path)?;
+                if let Some(down_path) = &file.down_path {
+                    fs::remove_file(down_path)?;
+                }
+                file.path
+            }
+        };
+
+        println!("Removed {}", path.display());
+    }
+
+    println!("Created {}", baseline_path.display());
+
+    Ok(0)
+}
+
+fn plan(options: &Options, migrations: &[Migration<'_>]) -> Result<i32, Box<dyn Error>> {
+    let mut conn = open_database(options)?;
+    let from = conn.latest_version(&tracking_table(options), &options.namespace)?;
+
+    let mut script = Script::with_namespace(Dialect::Sqlite, &options.namespace)
+        .with_tracking_table(tracking_table(options));
+    if !options.placeholders.is_empty() {
+        script = script.with_placeholders(options.placeholders.iter().cloned());
+    }
+
+    print!("{}", script.render(migrations.to_vec(), from, options.to)?);
+
+    Ok(0)
+}
+
+fn verify(options: &Options, migrations: &[Migration<'_>]) -> Result<i32, Box<dyn Error>> {
+    let mut conn = open_database(options)?;
+
+    let differences = runner(&mut conn, options)
+        .verify_schema(migrations.to_vec(), &mut Connection::open_in_memory()?)?;
+
+    if differences.is_empty() {
+        println!("Schema matches the applied migrations");
+        return Ok(0);
+    }
+
+    for difference in differences {
+        println!("{}", difference);
+    }
+
+    Ok(1)
+}
+
+fn new(options: &Options) -> Result<i32, Box<dyn Error>> {
+    require_nomad_convention(options)?;
+
+    let name = options
+        .args
+        .first()
+        .ok_or("Missing the name of the new migration")?;
+
+    let mut new = NewMigration::new(name)
+        .with_scheme(if options.timestamp {
+            VersionScheme::Timestamp
+        } else {
+            VersionScheme::Sequential
+        })
+        .with_down(options.down);
+    if let Some(header) = &options.header {
+        new = new.with_header(header);
+    }
+
+    let created = new.create(&options.migrations)?;
+    println!("Created {}", created.path.display());
+    if let Some(down_path) = created.down_path {
+        println!("Created {}", down_path.display());
+    }
+
+    Ok(0)
+}
+
+fn run(options: &Options) -> Result<i32, Box<dyn Error>> {
+    // New migrations only need the existing file names, and may start a missing directory
+    if options.command == "new" {
+        return new(options);
+    }
+
+    let migrations = load_dir_with(&options.migrations, &read_options(options))?;
+
+    match options.command.as_str() {
+        "status" => status(options, migrations.as_ref()),
+        "migrate" => migrate(options, migrations.as_ref()),
+        "rollback" => rollback(options, migrations.as_ref()),
+        "baseline" => baseline(options, migrations.as_ref()),
+        "plan" => plan(options, migrations.as_ref()),
+        "verify" => verify(options, migrations.as_ref()),
+        command => Err(format!("Unknown command {}\n\n{}", command, USAGE).into()),
+    }
+}
+
+fn main() {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            process::exit(2);
+        }
+    };
+
+    match run(&options) {
+        Ok(code) => process::exit(code),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
+    }
+}
@@ -1,9 +1,101 @@
+use crate::squash::BASELINE_NAME;
+use std::borrow::Cow;
 use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
 
-#[derive(Debug, Copy, Clone)]
+// Names and SQL are borrowed when embedded by nomad_migrations!, but can be owned when built at runtime
+#[derive(Debug, Clone)]
 pub struct Migration<'a> {
     pub version: u64,
-    pub sql: &'a str,
+    // Descriptive name, taken from the file name when embedded by nomad_migrations!
+    pub name: Cow<'a, str>,
+    pub sql: Cow<'a, str>,
+    // SQL reverting the migration, if it can be reverted
+    pub down: Option<Cow<'a, str>>,
+}
+
+impl<'a> Migration<'a> {
+    pub fn new<N: Into<Cow<'a, str>>, S: Into<Cow<'a, str>>>(
+        version: u64,
+        name: N,
+        sql: S,
+    ) -> Self {
+        Migration {
+            version,
+            name: name.into(),
+            sql: sql.into(),
+            down: None,
+        }
+    }
+
+    pub fn with_down<S: Into<Cow<'a, str>>>(mut self, down: S) -> Self {
+        self.down = Some(down.into());
+        self
+    }
+
+    pub fn is_baseline(&self) -> bool {
+        self.name == BASELINE_NAME
+    }
+
+    // CRC-32 of the SQL, as recorded alongside each applied migration
+    pub fn checksum(&self) -> u32 {
+        let mut crc = !0u32;
+        for byte in self.sql.bytes() {
+            crc ^= u32::from(byte);
+            for _ in 0..8 {
+                crc = if crc & 1 == 1 {
+                    (crc >> 1) ^ 0xEDB8_8320
+                } else {
+                    crc >> 1
+                };
+            }
+        }
+
+        !crc
+    }
+}
+
+// A record of a migration that has been applied to a database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedMigration {
+    pub version: u64,
+    pub name: String,
+    // Whether the migration was applied after a newer version had already been applied
+    pub out_of_order: bool,
+    // Checksum of the SQL that was run, or None if nomad didn't run it,
+    // such as when recorded before checksums were tracked, backfilled, or imported
+    pub checksum: Option<u32>,
+}
+
+impl AppliedMigration {
+    pub(crate) fn new(migration: &Migration<'_>, out_of_order: bool) -> Self {
+        AppliedMigration {
+            version: migration.version,
+            name: migration.name.to_string(),
+            out_of_order,
+            checksum: Some(migration.checksum()),
+        }
+    }
+
+    // A migration known to be applied, but not run by nomad, so there's no SQL to checksum
+    pub(crate) fn assumed(migration: &Migration<'_>) -> Self {
+        AppliedMigration {
+            checksum: None,
+            ..Self::new(migration, false)
+        }
+    }
+}
+
+impl Display for AppliedMigration {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.version, self.name)?;
+
+        if self.out_of_order {
+            write!(f, " (out of order)")?;
+        }
+
+        Ok(())
+    }
 }
 
 impl PartialEq for Migration<'_> {
@@ -33,6 +33,14 @@ where
         Some(OrderedArray(container, PhantomData))
     }
 
+    // Copy the contents into an owned container, preserving the ordering
+    pub fn to_vec(&self) -> OrderedArray<T, Vec<T>>
+    where
+        T: Clone,
+    {
+        OrderedArray(self.0.as_ref().to_vec(), PhantomData)
+    }
+
     pub fn iter(&self) -> slice::Iter<'_, T> {
         self.0.as_ref().iter()
     }
@@ -86,6 +94,8 @@ where
     }
 }
 
+// Duplicates are kept side by side, as they can't be rejected here without panicking.
+// Anything using the migrations checks for them, returning a DuplicateVersion error.
 impl<I: Ord, C: AsRef<[I]> + AsMut<[I]>> From<C> for OrderedArray<I, C> {
     fn from(mut container: C) -> Self {
         let slice = container.as_mut();
@@ -0,0 +1,88 @@
+// Substituting ${name} placeholders in migration SQL, so the same migrations can be applied
+// with a different table prefix or schema name for each tenant.
+// Only callers that opt in with placeholder values have their SQL rendered, so SQL that happens
+// to contain ${ still runs as written otherwise.
+use crate::{Migration, MigrationError};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::error::Error;
+
+// Replace every ${name} in the SQL with its value, and every $${ with a literal ${.
+// Returns None when the SQL has no placeholders, or the name of the first placeholder without a value.
+fn render_sql(sql: &str, placeholders: &HashMap<String, String>) -> Result<Option<String>, String> {
+    let mut rendered = String::new();
+    let mut rest = sql;
+
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            rendered.push_str(&rest[..start - 1]);
+            rendered.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+
+        // An unterminated placeholder is left as it is
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+
+        let name = &rest[start + 2..end];
+        let value = placeholders.get(name).ok_or_else(|| name.to_owned())?;
+
+        rendered.push_str(&rest[..start]);
+        rendered.push_str(value);
+        rest = &rest[end + 1..];
+    }
+
+    if rest.len() == sql.len() {
+        return Ok(None);
+    }
+
+    rendered.push_str(rest);
+    Ok(Some(rendered))
+}
+
+// SQL without placeholders stays borrowed
+fn render_cow<'a>(
+    sql: &Cow<'a, str>,
+    placeholders: &HashMap<String, String>,
+) -> Result<Cow<'a, str>, String> {
+    Ok(match render_sql(sql, placeholders)? {
+        Some(rendered) => Cow::Owned(rendered),
+        None => sql.clone(),
+    })
+}
+
+// Render the up and down SQL of every migration, keeping their order.
+// Without placeholders the migrations are left as they are.
+pub(crate) fn render<'a, E: Error>(
+    migrations: &[Migration<'a>],
+    placeholders: Option<&HashMap<String, String>>,
+) -> Result<Vec<Migration<'a>>, MigrationError<E>> {
+    let placeholders = match placeholders {
+        Some(placeholders) => placeholders,
+        None => return Ok(migrations.to_vec()),
+    };
+
+    migrations
+        .iter()
+        .map(|migration| {
+            let unknown = |placeholder| MigrationError::UnknownPlaceholder {
+                version: migration.version,
+                name: migration.name.to_string(),
+                placeholder,
+            };
+
+            Ok(Migration {
+                version: migration.version,
+                name: migration.name.clone(),
+                sql: render_cow(&migration.sql, placeholders).map_err(unknown)?,
+                down: match &migration.down {
+                    Some(down) => Some(render_cow(down, placeholders).map_err(unknown)?),
+                    None => None,
+                },
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,160 @@
+use crate::placeholder;
+use crate::{
+    migrate_namespace, AppliedMigration, Driver, DuplicateNamespace, Migration, MigrationError,
+    NamespaceState, OrderPolicy, OrderedMigrations, TrackingTable, Transaction,
+};
+use std::collections::HashMap;
+
+// The outcome of migrating a single namespace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationReport<'n> {
+    pub namespace: &'n str,
+    // The migrations applied to this namespace, in the order they were applied
+    pub applied: Vec<AppliedMigration>,
+}
+
+impl MigrationReport<'_> {
+    pub fn last_executed(&self) -> Option<u64> {
+        self.applied.last().map(|migration| migration.version)
+    }
+}
+
+// A collection of migration sets, each registered under its own namespace.
+// Allows several crates to contribute their migrations to a single database.
+#[derive(Default)]
+pub struct MigrationRegistry<'n, 'a> {
+    sets: Vec<(&'n str, OrderedMigrations<'a, Vec<Migration<'a>>>)>,
+    order_policy: OrderPolicy,
+    tracking_table: TrackingTable<'n>,
+    placeholders: Option<HashMap<String, String>>,
+    verify_checksums: bool,
+}
+
+impl<'n, 'a> MigrationRegistry<'n, 'a> {
+    pub fn new() -> Self {
+        MigrationRegistry {
+            sets: Vec::new(),
+            order_policy: OrderPolicy::default(),
+            tracking_table: TrackingTable::default(),
+            placeholders: None,
+            verify_checksums: false,
+        }
+    }
+
+    // Set how every namespace treats unapplied migrations older than its latest version
+    pub fn with_order_policy(&mut self, order_policy: OrderPolicy) -> &mut Self {
+        self.order_policy = order_policy;
+        self
+    }
+
+    // Set the tables used to track every namespace's applied migrations
+    pub fn with_tracking_table(&mut self, tracking_table: TrackingTable<'n>) -> &mut Self {
+        self.tracking_table = tracking_table;
+        self
+    }
+
+    // Substitute ${name} placeholders in every namespace's migrations,
+    // as MigrationRunner::with_placeholders does
+    pub fn with_placeholders<I, K, V>(&mut self, placeholders: I) -> &mut Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.placeholders.get_or_insert_with(HashMap::new).extend(
+            placeholders
+                .into_iter()
+                .map(|(name, value)| (name.into(), value.into())),
+        );
+        self
+    }
+
+    // Fail when any namespace's applied migrations have changed, as MigrationRunner::with_verify_checksums does
+    pub fn with_verify_checksums(&mut self, verify_checksums: bool) -> &mut Self {
+        self.verify_checksums = verify_checksums;
+        self
+    }
+
+    // Register a set of migrations under the given namespace,
+    // failing if the namespace has already been registered
+    pub fn register<T: AsRef<[Migration<'a>]>, C: Into<OrderedMigrations<'a, T>>>(
+        &mut self,
+        namespace: &'n str,
+        migrations: C,
+    ) -> Result<&mut Self, DuplicateNamespace> {
+        if self.sets.iter().any(|(existing, _)| *existing == namespace) {
+            return Err(DuplicateNamespace {
+                namespace: namespace.to_owned(),
+            });
+        }
+
+        self.sets.push((namespace, migrations.into().to_vec()));
+        Ok(self)
+    }
+
+    // Register several namespaces at once, such as the pairs produced by nomad_namespaces!.
+    // If any namespace has already been registered, none of them are.
+    pub fn register_all<I, T, C>(&mut self, sets: I) -> Result<&mut Self, DuplicateNamespace>
+    where
+        I: IntoIterator<Item = (&'n str, C)>,
+        T: AsRef<[Migration<'a>]>,
+        C: Into<OrderedMigrations<'a, T>>,
+    {
+        let registered = self.sets.len();
+        for (namespace, migrations) in sets {
+            if let Err(err) = self.register(namespace, migrations) {
+                self.sets.truncate(registered);
+                return Err(err);
+            }
+        }
+
+        Ok(self)
+    }
+
+    pub fn namespaces(&self) -> impl Iterator<Item = &'n str> + '_ {
+        self.sets.iter().map(|(namespace, _)| *namespace)
+    }
+
+    // Migrate every registered namespace, in registration order, within a single transaction.
+    // If any namespace fails, no changes are committed.
+    pub fn migrate_all<'d, D: Driver<'d>>(
+        &self,
+        driver: &'d mut D,
+    ) -> Result<Vec<MigrationReport<'n>>, MigrationError<D::Error>> {
+        // Rendering and reading state must happen before the transaction borrows the driver
+        let mut rendered = Vec::with_capacity(self.sets.len());
+        let mut states = Vec::with_capacity(self.sets.len());
+        for (namespace, migrations) in &self.sets {
+            rendered.push(placeholder::render(
+                migrations.as_ref(),
+                self.placeholders.as_ref(),
+            )?);
+            states.push(NamespaceState::read(
+                driver,
+                &self.tracking_table,
+                namespace,
+            )?);
+        }
+
+        let mut txn = driver.begin()?;
+
+        let mut reports = Vec::with_capacity(self.sets.len());
+        for (((namespace, _), migrations), state) in self.sets.iter().zip(rendered).zip(states) {
+            let applied = migrate_namespace::<D>(
+                &mut txn,
+                &self.tracking_table,
+                namespace,
+                &state,
+                self.order_policy,
+                self.verify_checksums,
+                &migrations,
+            )?;
+
+            reports.push(MigrationReport { namespace, applied });
+        }
+
+        txn.commit()?;
+
+        Ok(reports)
+    }
+}
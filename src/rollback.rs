@@ -0,0 +1,79 @@
+use crate::{
+    check_unique, AppliedMigration, Driver, Migration, MigrationError, MigrationRunner,
+    NamespaceState, OrderedMigrations, Transaction,
+};
+
+impl<'d, 'n, D: Driver<'d>> MigrationRunner<'d, 'n, D> {
+    // Revert every applied migration newer than the given version, newest first,
+    // by running their down migrations within a single transaction.
+    // A version of None reverts every applied migration.
+    // Returns the migrations that were reverted, in the order they were reverted.
+    pub fn rollback<'a, T: AsRef<[Migration<'a>]>, C: Into<OrderedMigrations<'a, T>>>(
+        self,
+        migrations: C,
+        to: Option<u64>,
+    ) -> Result<Vec<AppliedMigration>, MigrationError<D::Error>> {
+        let migrations = migrations.into();
+        let migrations = self.render(migrations.as_ref())?;
+        let migrations = migrations.as_ref();
+        check_unique(migrations)?;
+
+        let state = NamespaceState::read(self.driver, &self.tracking_table, self.namespace)?;
+
+        // Namespaces without history have applied every migration up to their latest version
+        let mut applied = if state.is_legacy() {
+            migrations
+                .iter()
+                .filter(|migration| Some(migration.version) <= state.latest_version)
+                .map(AppliedMigration::assumed)
+                .collect()
+        } else {
+            state.applied_migrations
+        };
+
+        let mut txn = self.driver.begin()?;
+
+        let mut reverted = Vec::new();
+        while let Some(record) = applied.pop() {
+            if Some(record.version) <= to {
+                break;
+            }
+
+            let migration = migrations
+                .binary_search_by_key(&record.version, |migration| migration.version)
+                .map(|index| &migrations[index])
+                .map_err(|_| MigrationError::UnknownVersion {
+                    version: record.version,
+                })?;
+
+            let down =
+                migration
+                    .down
+                    .as_ref()
+                    .ok_or_else(|| MigrationError::IrreversibleMigration {
+                        version: migration.version,
+                        name: migration.name.to_string(),
+                    })?;
+
+            txn.execute_sql(down)
+                .map_err(|source| MigrationError::ExecutionError {
+                    version: migration.version,
+                    name: migration.name.to_string(),
+                    source,
+                })?;
+
+            txn.remove_applied(
+                &self.tracking_table,
+                self.namespace,
+                record.version,
+                applied.last().map(|migration| migration.version),
+            )?;
+
+            reverted.push(record);
+        }
+
+        txn.commit()?;
+
+        Ok(reverted)
+    }
+}
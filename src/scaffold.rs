@@ -0,0 +1,137 @@
+// Creating new migration files, numbered after those already in a migrations directory
+use crate::Timestamp;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+pub use nomad_files::FileError;
+
+// How a new migration's version is chosen
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum VersionScheme {
+    // One more than the newest existing migration
+    Sequential,
+    // The current UTC time, as YYYYMMDDHHMMSS
+    Timestamp,
+}
+
+// The files written by `NewMigration::create`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreatedMigration {
+    pub version: u64,
+    pub path: PathBuf,
+    pub down_path: Option<PathBuf>,
+}
+
+pub struct NewMigration<'a> {
+    name: &'a str,
+    scheme: VersionScheme,
+    down: bool,
+    header: Option<&'a str>,
+}
+
+impl<'a> NewMigration<'a> {
+    pub fn new(name: &'a str) -> Self {
+        NewMigration {
+            name,
+            scheme: VersionScheme::Sequential,
+            down: false,
+            header: None,
+        }
+    }
+
+    pub fn with_scheme(mut self, scheme: VersionScheme) -> Self {
+        self.scheme = scheme;
+        self
+    }
+
+    // Also create a .down.sql file beside the migration
+    pub fn with_down(mut self, down: bool) -> Self {
+        self.down = down;
+        self
+    }
+
+    // Text written at the top of each new file.
+    // {version}, {name} and {direction} ("up" or "down") are replaced with the migration's details.
+    pub fn with_header(mut self, header: &'a str) -> Self {
+        self.header = Some(header);
+        self
+    }
+
+    // Pick the next version after the migrations in `dir`, read with the same rules as nomad_migrations!
+    pub fn next_version(&self, dir: &Path) -> Result<u64, FileError> {
+        let latest = if dir.exists() {
+            nomad_files::read_dir(dir)?
+                .last()
+                .map(|migration| migration.version)
+        } else {
+            None
+        };
+
+        let next = latest.map_or(1, |latest| latest + 1);
+
+        Ok(match self.scheme {
+            VersionScheme::Sequential => next,
+            // A clock behind the newest migration still has to sort after it
+            VersionScheme::Timestamp => Timestamp::now().version().max(next),
+        })
+    }
+
+    // Write the new migration's files to `dir`, creating it if needed.
+    // Existing files are never overwritten, so two migrations racing for a version fail instead.
+    pub fn create(&self, dir: &Path) -> Result<CreatedMigration, FileError> {
+        // The name must stay inside `dir`, and read back as an up migration
+        if self.name.is_empty()
+            || self.name.contains(['/', '\\'])
+            || self.name.ends_with(".up")
+            || self.name.ends_with(".down")
+        {
+            return Err(FileError::InvalidName(
+                dir.join(format!("{}.sql", self.name)),
+            ));
+        }
+
+        let version = self.next_version(dir)?;
+
+        fs::create_dir_all(dir).map_err(|source| FileError::Io {
+            path: dir.to_owned(),
+            source,
+        })?;
+
+        let path = dir.join(format!("{}_{}.sql", version, self.name));
+        self.write(&path, version, "up")?;
+
+        let down_path = if self.down {
+            let down_path = dir.join(format!("{}_{}.down.sql", version, self.name));
+            self.write(&down_path, version, "down")?;
+            Some(down_path)
+        } else {
+            None
+        };
+
+        Ok(CreatedMigration {
+            version,
+            path,
+            down_path,
+        })
+    }
+
+    fn write(&self, path: &Path, version: u64, direction: &str) -> Result<(), FileError> {
+        let contents = self.header.map_or(String::new(), |header| {
+            header
+                .replace("{version}", &version.to_string())
+                .replace("{name}", self.name)
+                .replace("{direction}", direction)
+        });
+
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(contents.as_bytes()))
+            .map_err(|source| FileError::Io {
+                path: path.to_owned(),
+                source,
+            })
+    }
+}
@@ -0,0 +1,158 @@
+use crate::driver::sqlite::{self, quote_literal};
+use crate::squash::since_baseline;
+use crate::{check_unique, Driver, Migration, MigrationError, TrackingTable, Transaction};
+use std::error::Error;
+
+// A table, index, view or trigger recorded in sqlite_master
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SchemaObject {
+    pub kind: String,
+    pub name: String,
+    // The table an index or trigger belongs to, or the object's own name
+    pub table: String,
+    pub sql: String,
+}
+
+// Apply migrations without tracking them, to build a scratch copy of the schema they produce
+pub(crate) fn apply<'a, D, E>(
+    driver: &mut D,
+    migrations: &[Migration<'a>],
+) -> Result<(), MigrationError<E>>
+where
+    D: for<'d> Driver<'d, Error = E>,
+    E: Error,
+{
+    check_unique(migrations)?;
+
+    let mut txn = driver.begin()?;
+    for migration in since_baseline(migrations) {
+        txn.execute_sql(&migration.sql)
+            .map_err(|source| MigrationError::ExecutionError {
+                version: migration.version,
+                name: migration.name.to_string(),
+                source,
+            })?;
+    }
+
+    txn.commit()?;
+    Ok(())
+}
+
+// Read every object created by migrations from sqlite_master.
+// Tables come first, as indexes, views and triggers depend on them.
+// Within each type, creation order keeps dependencies between views intact.
+pub(crate) fn read<'d, D: Driver<'d>>(driver: &mut D) -> Result<Vec<SchemaObject>, D::Error> {
+    let rows = driver.query_rows(
+        r#"
+SELECT type, name, tbl_name, sql FROM sqlite_master
+WHERE sql IS NOT NULL AND name NOT LIKE 'sqlite_%'
+ORDER BY CASE type WHEN 'table' THEN 0 WHEN 'index' THEN 1 WHEN 'view' THEN 2 ELSE 3 END, rowid
+"#,
+    )?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let mut values = row.into_iter().map(Option::unwrap_or_default);
+
+            SchemaObject {
+                kind: values.next().unwrap_or_default(),
+                name: values.next().unwrap_or_default(),
+                table: values.next().unwrap_or_default(),
+                sql: values.next().unwrap_or_default(),
+            }
+        })
+        .collect())
+}
+
+// Read the objects created by migrations, leaving out nomad's own tracking tables
+pub(crate) fn read_migrated<'d, D: Driver<'d>>(
+    driver: &mut D,
+    table: &TrackingTable<'_>,
+) -> Result<Vec<SchemaObject>, D::Error> {
+    let tracking = match table.schema {
+        Some(_) => Vec::new(),
+        None => sqlite::table_names(table).to_vec(),
+    };
+
+    Ok(read(driver)?
+        .into_iter()
+        .filter(|object| !tracking.contains(&object.table))
+        .collect())
+}
+
+// A column of a table, as described by pragma_table_info
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Column {
+    pub name: String,
+    // The column's type and constraints, such as "integer not null default 0"
+    pub definition: String,
+}
+
+pub(crate) fn columns<'d, D: Driver<'d>>(
+    driver: &mut D,
+    table: &str,
+) -> Result<Vec<Column>, D::Error> {
+    let rows = driver.query_rows(&format!(
+        r#"SELECT name, type, CAST("notnull" AS TEXT), dflt_value, CAST(pk AS TEXT) FROM pragma_table_info({}) ORDER BY cid"#,
+        quote_literal(table)
+    ))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let mut values = row.into_iter();
+            let mut next = || values.next().flatten();
+
+            let name = next().unwrap_or_default();
+            let mut definition = next().unwrap_or_default().to_lowercase();
+            if next().as_deref() == Some("1") {
+                definition.push_str(" not null");
+            }
+            if let Some(default) = next() {
+                definition.push_str(" default ");
+                definition.push_str(&default);
+            }
+            if matches!(next().as_deref(), Some(pk) if pk != "0") {
+                definition.push_str(" primary key");
+            }
+
+            Column {
+                name,
+                definition: definition.trim().to_owned(),
+            }
+        })
+        .collect())
+}
+
+// Collapse runs of whitespace to a single space, dropping any just inside brackets or before commas.
+// Quoted names and strings are left untouched.
+pub(crate) fn normalize(sql: &str) -> String {
+    let mut normalized = String::with_capacity(sql.len());
+    let mut quote = None;
+    let mut space = false;
+
+    for c in sql.trim().chars() {
+        if quote.is_none() && c.is_whitespace() {
+            space = true;
+            continue;
+        }
+
+        if space && c != ')' && c != ',' && !normalized.ends_with('(') {
+            normalized.push(' ');
+        }
+        space = false;
+
+        match (quote, c) {
+            // A doubled quote inside a quoted section closes and immediately reopens it
+            (Some(open), c) if c == open => quote = None,
+            (None, '"') | (None, '\'') | (None, '`') => quote = Some(c),
+            (None, '[') => quote = Some(']'),
+            _ => {}
+        }
+
+        normalized.push(c);
+    }
+
+    normalized
+}
@@ -0,0 +1,327 @@
+use crate::driver::sqlite;
+use crate::placeholder;
+use crate::{
+    migrate_namespace, AppliedMigration, Driver, Migration, MigrationError, NamespaceState,
+    OrderPolicy, OrderedMigrations, TrackingTable, Transaction, DEFAULT_NAMESPACE,
+};
+use std::collections::HashMap;
+use std::fmt;
+
+// The SQL dialect a script is written in
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Dialect {
+    Sqlite,
+}
+
+// Renders migrations to a standalone SQL script, to be reviewed and run outside of nomad.
+// The script makes the same changes, including to the tracking tables, as `MigrationRunner::migrate`.
+// The tracking tables must either not exist yet, or already be laid out by this version of nomad.
+pub struct Script<'n> {
+    pub dialect: Dialect,
+    pub namespace: &'n str,
+    pub tracking_table: TrackingTable<'n>,
+    // Values for ${name} placeholders in migration SQL, as given to MigrationRunner::with_placeholders
+    pub placeholders: Option<HashMap<String, String>>,
+}
+
+impl Script<'static> {
+    pub fn new(dialect: Dialect) -> Self {
+        Self::with_namespace(dialect, DEFAULT_NAMESPACE)
+    }
+}
+
+impl<'n> Script<'n> {
+    pub fn with_namespace(dialect: Dialect, namespace: &'n str) -> Self {
+        Script {
+            dialect,
+            namespace,
+            tracking_table: TrackingTable::default(),
+            placeholders: None,
+        }
+    }
+
+    pub fn with_tracking_table(mut self, tracking_table: TrackingTable<'n>) -> Self {
+        self.tracking_table = tracking_table;
+        self
+    }
+
+    // Substitute ${name} placeholders before writing each migration, as MigrationRunner::with_placeholders does
+    pub fn with_placeholders<I, K, V>(mut self, placeholders: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.placeholders.get_or_insert_with(HashMap::new).extend(
+            placeholders
+                .into_iter()
+                .map(|(name, value)| (name.into(), value.into())),
+        );
+        self
+    }
+
+    // Render the migrations needed to take a database at version `from` to version `to`.
+    // `from` is None for a database that has never been migrated, `to` is None for the latest migration.
+    pub fn render<'a, T: AsRef<[Migration<'a>]>, C: Into<OrderedMigrations<'a, T>>>(
+        &self,
+        migrations: C,
+        from: Option<u64>,
+        to: Option<u64>,
+    ) -> Result<String, MigrationError<fmt::Error>> {
+        let migrations = migrations.into();
+        let migrations = placeholder::render(migrations.as_ref(), self.placeholders.as_ref())?;
+        let migrations = migrations.as_slice();
+
+        // Migrations are ordered, so those up to the target version form a prefix
+        let pending = match to {
+            Some(to) => {
+                &migrations[..migrations
+                    .iter()
+                    .take_while(|migration| migration.version <= to)
+                    .count()]
+            }
+            None => migrations,
+        };
+
+        let mut driver = ScriptDriver {
+            dialect: self.dialect,
+            tracking_tables_written: false,
+            latest_version: from,
+            applied: migrations
+                .iter()
+                .take_while(|migration| Some(migration.version) <= from)
+                .map(AppliedMigration::assumed)
+                .collect(),
+            sql: String::new(),
+        };
+
+        let state = NamespaceState::read(&mut driver, &self.tracking_table, self.namespace)?;
+
+        let mut txn = driver.begin()?;
+        migrate_namespace::<ScriptDriver>(
+            &mut txn,
+            &self.tracking_table,
+            self.namespace,
+            &state,
+            OrderPolicy::Strict,
+            false,
+            pending,
+        )?;
+        txn.commit()?;
+
+        Ok(driver.sql)
+    }
+}
+
+// Render migrations for the default namespace and tracking table
+pub fn render<'a, T: AsRef<[Migration<'a>]>, C: Into<OrderedMigrations<'a, T>>>(
+    migrations: C,
+    from: Option<u64>,
+    to: Option<u64>,
+    dialect: Dialect,
+) -> Result<String, MigrationError<fmt::Error>> {
+    Script::new(dialect).render(migrations, from, to)
+}
+
+// A driver which writes statements to a script instead of executing them,
+// against a database assumed to have applied every migration up to its latest version
+struct ScriptDriver {
+    dialect: Dialect,
+    tracking_tables_written: bool,
+    latest_version: Option<u64>,
+    applied: Vec<AppliedMigration>,
+    sql: String,
+}
+
+impl ScriptDriver {
+    fn write(&mut self, sql: &str) {
+        let sql = sql.trim();
+        if sql.is_empty() {
+            return;
+        }
+
+        self.sql.push_str(sql);
+
+        // Statements are separated by semicolons, which a single migration may leave off.
+        // A new line keeps the separator out of any trailing comment.
+        if !sql.ends_with(';') {
+            self.sql.push_str("\n;");
+        }
+
+        self.sql.push('\n');
+    }
+}
+
+struct ScriptTransaction<'t> {
+    driver: &'t mut ScriptDriver,
+}
+
+impl<'t> Driver<'t> for ScriptDriver {
+    type Transaction = ScriptTransaction<'t>;
+    type Error = fmt::Error;
+
+    fn begin(&'t mut self) -> Result<Self::Transaction, Self::Error> {
+        match self.dialect {
+            Dialect::Sqlite => self.write("BEGIN;"),
+        }
+
+        Ok(ScriptTransaction { driver: self })
+    }
+
+    fn latest_version(
+        &mut self,
+        _table: &TrackingTable<'_>,
+        _namespace: &str,
+    ) -> Result<Option<u64>, Self::Error> {
+        Ok(self.latest_version)
+    }
+
+    fn applied_migrations(
+        &mut self,
+        _table: &TrackingTable<'_>,
+        _namespace: &str,
+    ) -> Result<Vec<AppliedMigration>, Self::Error> {
+        Ok(self.applied.clone())
+    }
+
+    fn query_rows(&mut self, _sql: &str) -> Result<Vec<Vec<Option<String>>>, Self::Error> {
+        Ok(Vec::new())
+    }
+}
+
+impl ScriptTransaction<'_> {
+    // Creating the tracking tables is written before the first statement that uses them
+    fn ensure_tracking_tables(&mut self, table: &TrackingTable<'_>) {
+        if self.driver.tracking_tables_written {
+            return;
+        }
+
+        self.driver.tracking_tables_written = true;
+
+        match self.driver.dialect {
+            Dialect::Sqlite => self.driver.write(&sqlite::current_layout_def(table)),
+        }
+    }
+}
+
+impl<'t> Transaction<'t, ScriptDriver> for ScriptTransaction<'t> {
+    fn commit(self) -> Result<(), fmt::Error> {
+        match self.driver.dialect {
+            Dialect::Sqlite => self.driver.write("COMMIT;"),
+        }
+
+        Ok(())
+    }
+
+    fn execute_sql(&mut self, sql: &str) -> Result<(), fmt::Error> {
+        self.driver.write(sql);
+        Ok(())
+    }
+
+    fn push_latest_version(
+        &mut self,
+        table: &TrackingTable<'_>,
+        namespace: &str,
+        version: u64,
+    ) -> Result<(), fmt::Error> {
+        self.ensure_tracking_tables(table);
+
+        let sql = match self.driver.dialect {
+            Dialect::Sqlite => bind(
+                &sqlite::upsert_latest_version(table),
+                &[
+                    sqlite::quote_literal(namespace),
+                    (version as i64).to_string(),
+                ],
+            ),
+        };
+
+        self.driver.write(&format!("{};", sql));
+        Ok(())
+    }
+
+    fn record_applied(
+        &mut self,
+        table: &TrackingTable<'_>,
+        namespace: &str,
+        migration: &AppliedMigration,
+    ) -> Result<(), fmt::Error> {
+        self.ensure_tracking_tables(table);
+
+        let sql = match self.driver.dialect {
+            Dialect::Sqlite => bind(
+                &sqlite::insert_applied(table),
+                &[
+                    sqlite::quote_literal(namespace),
+                    (migration.version as i64).to_string(),
+                    sqlite::quote_literal(&migration.name),
+                    (migration.out_of_order as i64).to_string(),
+                    match migration.checksum {
+                        Some(checksum) => checksum.to_string(),
+                        None => "NULL".to_owned(),
+                    },
+                ],
+            ),
+        };
+
+        self.driver.write(&format!("{};", sql));
+        Ok(())
+    }
+
+    fn remove_applied(
+        &mut self,
+        table: &TrackingTable<'_>,
+        namespace: &str,
+        version: u64,
+        latest_version: Option<u64>,
+    ) -> Result<(), fmt::Error> {
+        self.ensure_tracking_tables(table);
+
+        let namespace = sqlite::quote_literal(namespace);
+        let statements = match self.driver.dialect {
+            Dialect::Sqlite => vec![
+                bind(
+                    &sqlite::delete_applied(table),
+                    &[namespace.clone(), (version as i64).to_string()],
+                ),
+                match latest_version {
+                    Some(latest_version) => bind(
+                        &sqlite::upsert_latest_version(table),
+                        &[namespace, (latest_version as i64).to_string()],
+                    ),
+                    None => bind(&sqlite::delete_latest_version(table), &[namespace]),
+                },
+            ],
+        };
+
+        for sql in statements {
+            self.driver.write(&format!("{};", sql));
+        }
+
+        Ok(())
+    }
+}
+
+// Replace each `?` parameter with a literal, leaving quoted identifiers and strings untouched
+fn bind(sql: &str, values: &[String]) -> String {
+    let mut bound = String::with_capacity(sql.len());
+    let mut values = values.iter();
+    let mut quote = None;
+
+    for c in sql.chars() {
+        match (quote, c) {
+            // A doubled quote inside a quoted section closes and immediately reopens it
+            (Some(open), c) if c == open => quote = None,
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '?') => {
+                bound.push_str(values.next().expect("More parameters than values"));
+                continue;
+            }
+            _ => {}
+        }
+
+        bound.push(c);
+    }
+
+    bound
+}
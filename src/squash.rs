@@ -0,0 +1,101 @@
+use crate::schema;
+use crate::{check_unique, Driver, Migration, MigrationError, OrderedMigrations};
+use std::borrow::Cow;
+use std::error::Error;
+
+// The name marking a migration as a baseline, squashing every older migration into one.
+// Fresh databases run the baseline in place of the migrations it replaces,
+// while databases that have already passed its version skip it.
+pub const BASELINE_NAME: &str = "baseline";
+
+// A baseline migration produced by `squash`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Baseline {
+    pub version: u64,
+    pub sql: String,
+}
+
+impl Baseline {
+    // The file name nomad_migrations! recognises as this baseline
+    pub fn file_name(&self) -> String {
+        format!("{}_{}.sql", self.version, BASELINE_NAME)
+    }
+
+    pub fn migration(&self) -> Migration<'_> {
+        Migration {
+            version: self.version,
+            name: Cow::Borrowed(BASELINE_NAME),
+            sql: Cow::Borrowed(&self.sql),
+            down: None,
+        }
+    }
+}
+
+// Skip any migrations older than the newest baseline, as it already covers them
+pub(crate) fn since_baseline<'m, 'a>(migrations: &'m [Migration<'a>]) -> &'m [Migration<'a>] {
+    let start = migrations
+        .iter()
+        .rev()
+        .find(|migration| migration.is_baseline())
+        .and_then(|baseline| {
+            migrations
+                .iter()
+                .position(|migration| migration.version == baseline.version)
+        });
+
+    match start {
+        Some(start) => &migrations[start..],
+        None => migrations,
+    }
+}
+
+// Squash every migration up to and including version `through` into a single baseline.
+// The migrations are applied to the driver's database, which must be an empty SQLite database,
+// and the resulting schema is read back from sqlite_master. Only the schema is captured,
+// so any data inserted by the squashed migrations must be added to the baseline by hand.
+pub fn squash<'a, T, C, D, E>(
+    driver: &mut D,
+    migrations: C,
+    through: u64,
+) -> Result<Baseline, MigrationError<E>>
+where
+    T: AsRef<[Migration<'a>]>,
+    C: Into<OrderedMigrations<'a, T>>,
+    D: for<'d> Driver<'d, Error = E>,
+    E: Error,
+{
+    let migrations = migrations.into();
+    let migrations = migrations.as_ref();
+    check_unique(migrations)?;
+
+    if !migrations
+        .iter()
+        .any(|migration| migration.version == through)
+    {
+        return Err(MigrationError::UnknownVersion { version: through });
+    }
+
+    let squashed = &migrations[..migrations
+        .iter()
+        .take_while(|migration| migration.version <= through)
+        .count()];
+
+    schema::apply(driver, squashed)?;
+
+    let mut sql = format!(
+        "-- Baseline squashing migrations {} through {}\n",
+        since_baseline(squashed)
+            .first()
+            .map_or(through, |migration| migration.version),
+        through
+    );
+    for object in schema::read(driver)? {
+        sql.push_str(&object.sql);
+        sql.push_str(";\n");
+    }
+
+    Ok(Baseline {
+        version: through,
+        sql,
+    })
+}
@@ -0,0 +1,317 @@
+// Helpers for testing a crate's migrations from its own test suite
+use crate::schema::{self, SchemaObject};
+use crate::{
+    Driver, Migration, MigrationError, MigrationRunner, OrderedMigrations, TrackingTable,
+    Transaction,
+};
+use std::env;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::Path;
+
+// Set this environment variable to write snapshots instead of comparing against them
+pub const UPDATE_SNAPSHOT_VAR: &str = "NOMAD_UPDATE_SNAPSHOT";
+
+// Apply migrations to an empty SQLite database and dump the schema they produce.
+// Objects are sorted by type then name, and whitespace is normalized,
+// so that the dump only changes when the schema itself does.
+pub fn schema_snapshot<'a, T, C, D, E>(
+    driver: &mut D,
+    migrations: C,
+) -> Result<String, MigrationError<E>>
+where
+    T: AsRef<[Migration<'a>]>,
+    C: Into<OrderedMigrations<'a, T>>,
+    D: for<'d> Driver<'d, Error = E>,
+    E: Error,
+{
+    schema::apply(driver, migrations.into().as_ref())?;
+
+    Ok(format_snapshot(schema::read(driver)?))
+}
+
+fn format_snapshot(mut objects: Vec<SchemaObject>) -> String {
+    fn rank(object: &SchemaObject) -> u8 {
+        match object.kind.as_str() {
+            "table" => 0,
+            "index" => 1,
+            "view" => 2,
+            _ => 3,
+        }
+    }
+
+    objects.sort_by(|a, b| rank(a).cmp(&rank(b)).then_with(|| a.name.cmp(&b.name)));
+
+    objects
+        .iter()
+        .map(|object| format!("{};\n", schema::normalize(&object.sql)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Compare the schema produced by migrations with a checked in snapshot, panicking with a diff if they differ.
+// When NOMAD_UPDATE_SNAPSHOT is set, the snapshot is written instead.
+pub fn assert_schema_snapshot<'a, T, C, D, E, P>(driver: &mut D, migrations: C, snapshot: P)
+where
+    T: AsRef<[Migration<'a>]>,
+    C: Into<OrderedMigrations<'a, T>>,
+    D: for<'d> Driver<'d, Error = E>,
+    E: Error,
+    P: AsRef<Path>,
+{
+    let snapshot = snapshot.as_ref();
+    let actual = schema_snapshot(driver, migrations)
+        .unwrap_or_else(|err| panic!("Failed to apply migrations: {}", err));
+
+    if env::var_os(UPDATE_SNAPSHOT_VAR).is_some() {
+        fs::write(snapshot, &actual).unwrap_or_else(|err| {
+            panic!(
+                "Failed to write schema snapshot {}: {}",
+                snapshot.display(),
+                err
+            )
+        });
+        return;
+    }
+
+    let expected = fs::read_to_string(snapshot).unwrap_or_else(|err| {
+        panic!(
+            "Failed to read schema snapshot {}: {}\nRun with {}=1 to create it",
+            snapshot.display(),
+            err,
+            UPDATE_SNAPSHOT_VAR
+        )
+    });
+
+    if expected != actual {
+        panic!(
+            "Schema differs from snapshot {}\nRun with {}=1 to update it\n\n{}",
+            snapshot.display(),
+            UPDATE_SNAPSHOT_VAR,
+            diff(&expected, &actual)
+        );
+    }
+}
+
+// A problem found with a single migration by a MigrationHarness
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationFailure {
+    pub version: u64,
+    pub name: String,
+    pub reason: String,
+}
+
+impl Display for MigrationFailure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Migration {} {}: {}",
+            self.version, self.name, self.reason
+        )
+    }
+}
+
+// Checks each migration in a set against its own fresh SQLite database, opened by `open`
+pub struct MigrationHarness<F> {
+    open: F,
+    check_down: bool,
+}
+
+impl<F> MigrationHarness<F> {
+    pub fn new(open: F) -> Self {
+        MigrationHarness {
+            open,
+            check_down: false,
+        }
+    }
+
+    // Also check that each down migration reverts the schema, and that the migration can then be applied again
+    pub fn with_down_migrations(mut self, check_down: bool) -> Self {
+        self.check_down = check_down;
+        self
+    }
+}
+
+impl<F, D, E> MigrationHarness<F>
+where
+    F: FnMut() -> D,
+    D: for<'d> Driver<'d, Error = E>,
+    E: Error,
+{
+    // For each migration, apply it along with every migration before it,
+    // then check that applying them again is a no-op.
+    // Checking stops at the first migration which can't be applied, as none after it can be either.
+    // Returns every failure found, so no failures means every migration passed.
+    pub fn run<'a, T: AsRef<[Migration<'a>]>, C: Into<OrderedMigrations<'a, T>>>(
+        &mut self,
+        migrations: C,
+    ) -> Vec<MigrationFailure> {
+        let migrations = migrations.into();
+        let migrations = migrations.as_ref();
+
+        let mut failures = Vec::new();
+        let mut before = String::new();
+        for (index, migration) in migrations.iter().enumerate() {
+            let mut fail = |reason: String| {
+                failures.push(MigrationFailure {
+                    version: migration.version,
+                    name: migration.name.to_string(),
+                    reason,
+                })
+            };
+
+            match self.check(&migrations[..=index], &before, &mut fail) {
+                Some(after) => before = after,
+                None => break,
+            }
+        }
+
+        failures
+    }
+
+    // Run every check, panicking with every failure found
+    pub fn assert<'a, T: AsRef<[Migration<'a>]>, C: Into<OrderedMigrations<'a, T>>>(
+        &mut self,
+        migrations: C,
+    ) {
+        let failures = self.run(migrations);
+
+        if !failures.is_empty() {
+            panic!(
+                "{} migration checks failed\n\n{}",
+                failures.len(),
+                failures
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join("\n\n")
+            );
+        }
+    }
+
+    // Check the last of the given migrations, given the schema produced by the rest.
+    // Returns the schema produced by all of them, or None if they couldn't be applied.
+    fn check(
+        &mut self,
+        migrations: &[Migration<'_>],
+        before: &str,
+        fail: &mut dyn FnMut(String),
+    ) -> Option<String> {
+        let migration = migrations.last()?;
+        let table = TrackingTable::default();
+        let mut driver = (self.open)();
+
+        let snapshot = |driver: &mut D, fail: &mut dyn FnMut(String)| {
+            schema::read_migrated(driver, &table)
+                .map(format_snapshot)
+                .map_err(|err| fail(format!("Failed to read the schema: {}", err)))
+                .ok()
+        };
+
+        if let Err(err) = MigrationRunner::new(&mut driver).migrate(migrations.to_vec()) {
+            fail(format!("Failed to apply: {}", err));
+            return None;
+        }
+
+        let after = snapshot(&mut driver, fail)?;
+
+        match MigrationRunner::new(&mut driver).migrate(migrations.to_vec()) {
+            Ok(None) => {}
+            Ok(Some(version)) => fail(format!(
+                "Applying again executed version {} a second time",
+                version
+            )),
+            Err(err) => fail(format!("Failed to apply again: {}", err)),
+        }
+
+        if let Some(again) = snapshot(&mut driver, fail) {
+            if again != after {
+                fail(format!(
+                    "Applying again changed the schema\n{}",
+                    diff(&after, &again)
+                ));
+            }
+        }
+
+        let down = match &migration.down {
+            Some(down) if self.check_down => down,
+            _ => return Some(after),
+        };
+
+        for (step, sql, expected) in [
+            ("down migration", down.as_ref(), before),
+            (
+                "migration after its down migration",
+                migration.sql.as_ref(),
+                after.as_str(),
+            ),
+        ]
+        .iter()
+        {
+            if let Err(err) = execute(&mut driver, sql) {
+                fail(format!("Failed to apply the {}: {}", step, err));
+                break;
+            }
+
+            match snapshot(&mut driver, fail) {
+                Some(actual) if actual != *expected => {
+                    fail(format!(
+                        "The {} left a different schema\n{}",
+                        step,
+                        diff(expected, &actual)
+                    ));
+                    break;
+                }
+                Some(_) => {}
+                None => break,
+            }
+        }
+
+        Some(after)
+    }
+}
+
+// Execute SQL directly, leaving the tracking tables untouched
+fn execute<'d, D: Driver<'d>>(driver: &'d mut D, sql: &str) -> Result<(), D::Error> {
+    let mut txn = driver.begin()?;
+    txn.execute_sql(sql)?;
+    txn.commit()
+}
+
+// A line by line diff, with removed lines prefixed by '-' and added lines by '+'
+fn diff(expected: &str, actual: &str) -> String {
+    let expected = expected.lines().collect::<Vec<_>>();
+    let actual = actual.lines().collect::<Vec<_>>();
+
+    // Length of the longest common subsequence of each pair of suffixes
+    let mut common = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            common[i][j] = if expected[i] == actual[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            diff.push_str(&format!("  {}\n", expected[i]));
+            i += 1;
+            j += 1;
+        } else if j < actual.len() && (i == expected.len() || common[i][j + 1] >= common[i + 1][j])
+        {
+            diff.push_str(&format!("+ {}\n", actual[j]));
+            j += 1;
+        } else {
+            diff.push_str(&format!("- {}\n", expected[i]));
+            i += 1;
+        }
+    }
+
+    diff
+}
@@ -0,0 +1,127 @@
+use std::fmt::{Display, Formatter};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// A UTC timestamp used as a migration version, written as YYYYMMDDHHMMSS.
+// Timestamp versions let migrations be created on separate branches without
+// contending for the next sequential number.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+fn is_leap_year(year: u16) -> bool {
+    match (year % 4, year % 100, year % 400) {
+        (_, _, 0) => true,
+        (_, 0, _) => false,
+        (0, _, _) => true,
+        _ => false,
+    }
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl Timestamp {
+    // Interpret a version as a timestamp.
+    // Returns None if the version isn't a valid 14 digit timestamp.
+    pub fn from_version(version: u64) -> Option<Self> {
+        if !(10_000_000_000_000..100_000_000_000_000).contains(&version) {
+            return None;
+        }
+
+        let timestamp = Timestamp {
+            year: (version / 10_000_000_000) as u16,
+            month: (version / 100_000_000 % 100) as u8,
+            day: (version / 1_000_000 % 100) as u8,
+            hour: (version / 10_000 % 100) as u8,
+            minute: (version / 100 % 100) as u8,
+            second: (version % 100) as u8,
+        };
+
+        if timestamp.is_valid() {
+            Some(timestamp)
+        } else {
+            None
+        }
+    }
+
+    // Convert a point in time to a timestamp, in UTC.
+    // Times before the Unix epoch are clamped to the epoch.
+    pub fn from_system_time(time: SystemTime) -> Self {
+        let seconds = time
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+
+        // Convert days since the epoch to a civil date (Howard Hinnant's algorithm)
+        let days = (seconds / 86_400) as i64 + 719_468;
+        let era = days / 146_097;
+        let day_of_era = days - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        let seconds_of_day = seconds % 86_400;
+
+        Timestamp {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+            hour: (seconds_of_day / 3600) as u8,
+            minute: (seconds_of_day / 60 % 60) as u8,
+            second: (seconds_of_day % 60) as u8,
+        }
+    }
+
+    pub fn now() -> Self {
+        Self::from_system_time(SystemTime::now())
+    }
+
+    // The version number for this timestamp
+    pub fn version(&self) -> u64 {
+        self.year as u64 * 10_000_000_000
+            + self.month as u64 * 100_000_000
+            + self.day as u64 * 1_000_000
+            + self.hour as u64 * 10_000
+            + self.minute as u64 * 100
+            + self.second as u64
+    }
+
+    fn is_valid(&self) -> bool {
+        (1..=12).contains(&self.month)
+            && self.day >= 1
+            && self.day <= days_in_month(self.year, self.month)
+            && self.hour < 24
+            && self.minute < 60
+            && self.second < 60
+    }
+}
+
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
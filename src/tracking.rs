@@ -0,0 +1,31 @@
+// The tables nomad uses to track applied migrations.
+// The history table shares the name of the tracking table, suffixed with "_history".
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TrackingTable<'t> {
+    // Schema (or attached database, on SQLite) holding the tables, if not the default
+    pub schema: Option<&'t str>,
+    pub name: &'t str,
+}
+
+pub const DEFAULT_TRACKING_TABLE: &str = "nomad_migrations";
+
+impl<'t> TrackingTable<'t> {
+    pub fn new(name: &'t str) -> Self {
+        TrackingTable { schema: None, name }
+    }
+
+    pub fn with_schema(mut self, schema: &'t str) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    pub fn history_name(&self) -> String {
+        format!("{}_history", self.name)
+    }
+}
+
+impl Default for TrackingTable<'_> {
+    fn default() -> Self {
+        TrackingTable::new(DEFAULT_TRACKING_TABLE)
+    }
+}
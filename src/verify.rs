@@ -0,0 +1,257 @@
+use crate::schema::{self, SchemaObject};
+use crate::squash::since_baseline;
+use crate::{
+    check_unique, Driver, Migration, MigrationError, MigrationRunner, NamespaceState,
+    OrderedMigrations,
+};
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+
+// A difference between a live database's schema and the schema its applied migrations produce
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaDifference {
+    // An object created by the migrations is missing from the database
+    MissingObject {
+        kind: String,
+        name: String,
+    },
+    // An object in the database wasn't created by any migration
+    ExtraObject {
+        kind: String,
+        name: String,
+    },
+    // An object is defined differently to how the migrations created it
+    ChangedObject {
+        kind: String,
+        name: String,
+        expected: String,
+        actual: String,
+    },
+    MissingColumn {
+        table: String,
+        column: String,
+    },
+    ExtraColumn {
+        table: String,
+        column: String,
+    },
+    ChangedColumn {
+        table: String,
+        column: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+impl Display for SchemaDifference {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingObject { kind, name } => write!(f, "Missing {} {}", kind, name),
+            Self::ExtraObject { kind, name } => {
+                write!(
+                    f,
+                    "Unexpected {} {}, not created by a migration",
+                    kind, name
+                )
+            }
+            Self::ChangedObject {
+                kind,
+                name,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Changed {} {}: expected \"{}\", found \"{}\"",
+                kind, name, expected, actual
+            ),
+            Self::MissingColumn { table, column } => {
+                write!(f, "Missing column {}.{}", table, column)
+            }
+            Self::ExtraColumn { table, column } => write!(
+                f,
+                "Unexpected column {}.{}, not created by a migration",
+                table, column
+            ),
+            Self::ChangedColumn {
+                table,
+                column,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Changed column {}.{}: expected \"{}\", found \"{}\"",
+                table, column, expected, actual
+            ),
+        }
+    }
+}
+
+impl<'d, 'n, D: Driver<'d>> MigrationRunner<'d, 'n, D> {
+    // Compare the live database's schema with the schema its applied migrations should have produced,
+    // reporting any changes made outside of nomad, such as hotfixed indexes or columns.
+    // Other namespaces may share the database, so extra objects are only reported on tables
+    // this namespace's migrations created at some point.
+    // The applied migrations are replayed against `scratch`, which must be an empty SQLite database.
+    // Returns no differences when the schemas match.
+    pub fn verify_schema<'a, T, C, S>(
+        self,
+        migrations: C,
+        scratch: &mut S,
+    ) -> Result<Vec<SchemaDifference>, MigrationError<D::Error>>
+    where
+        T: AsRef<[Migration<'a>]>,
+        C: Into<OrderedMigrations<'a, T>>,
+        S: for<'s> Driver<'s, Error = D::Error>,
+    {
+        let migrations = migrations.into();
+        let migrations = self.render(migrations.as_ref())?;
+        let migrations = migrations.as_ref();
+        check_unique(migrations)?;
+
+        let state = NamespaceState::read(self.driver, &self.tracking_table, self.namespace)?;
+
+        // Versions older than a baseline were squashed into it, so won't be known migrations
+        let baseline = since_baseline(migrations)
+            .first()
+            .filter(|migration| migration.is_baseline())
+            .map(|migration| migration.version);
+
+        for applied in &state.applied_migrations {
+            if Some(applied.version) < baseline {
+                continue;
+            }
+
+            if migrations
+                .binary_search_by_key(&applied.version, |migration| migration.version)
+                .is_err()
+            {
+                return Err(MigrationError::UnknownVersion {
+                    version: applied.version,
+                });
+            }
+        }
+
+        let applied = migrations
+            .iter()
+            .filter(|migration| {
+                if state.is_legacy() {
+                    Some(migration.version) <= state.latest_version
+                } else {
+                    state.is_applied(migration.version)
+                }
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+
+        // Replayed one at a time, so tables dropped by a later migration are still known
+        let mut touched = HashSet::new();
+        for migration in since_baseline(&applied) {
+            schema::apply(scratch, std::slice::from_ref(migration))?;
+            touched.extend(
+                schema::read(scratch)?
+                    .into_iter()
+                    .map(|object| object.table),
+            );
+        }
+
+        let expected = schema::read(scratch)?;
+
+        let actual = schema::read_migrated(self.driver, &self.tracking_table)?;
+
+        let find = |objects: &[SchemaObject], object: &SchemaObject| {
+            objects
+                .iter()
+                .find(|other| other.kind == object.kind && other.name == object.name)
+                .cloned()
+        };
+
+        let mut differences = Vec::new();
+        for expected in &expected {
+            let actual = match find(&actual, expected) {
+                Some(actual) => actual,
+                None => {
+                    differences.push(SchemaDifference::MissingObject {
+                        kind: expected.kind.clone(),
+                        name: expected.name.clone(),
+                    });
+                    continue;
+                }
+            };
+
+            // Column differences explain a changed table better than its whole definition
+            if expected.kind == "table" {
+                let found = differences.len();
+                differences.extend(compare_columns(
+                    &expected.name,
+                    &schema::columns(scratch, &expected.name)?,
+                    &schema::columns(self.driver, &actual.name)?,
+                ));
+
+                if differences.len() > found {
+                    continue;
+                }
+            }
+
+            let (expected_sql, actual_sql) = (
+                schema::normalize(&expected.sql),
+                schema::normalize(&actual.sql),
+            );
+            if expected_sql != actual_sql {
+                differences.push(SchemaDifference::ChangedObject {
+                    kind: expected.kind.clone(),
+                    name: expected.name.clone(),
+                    expected: expected_sql,
+                    actual: actual_sql,
+                });
+            }
+        }
+
+        for actual in &actual {
+            if touched.contains(&actual.table) && find(&expected, actual).is_none() {
+                differences.push(SchemaDifference::ExtraObject {
+                    kind: actual.kind.clone(),
+                    name: actual.name.clone(),
+                });
+            }
+        }
+
+        Ok(differences)
+    }
+}
+
+fn compare_columns(
+    table: &str,
+    expected: &[schema::Column],
+    actual: &[schema::Column],
+) -> Vec<SchemaDifference> {
+    let mut differences = Vec::new();
+
+    for expected in expected {
+        match actual.iter().find(|column| column.name == expected.name) {
+            None => differences.push(SchemaDifference::MissingColumn {
+                table: table.to_owned(),
+                column: expected.name.clone(),
+            }),
+            Some(actual) if actual.definition != expected.definition => {
+                differences.push(SchemaDifference::ChangedColumn {
+                    table: table.to_owned(),
+                    column: expected.name.clone(),
+                    expected: expected.definition.clone(),
+                    actual: actual.definition.clone(),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+
+    for actual in actual {
+        if !expected.iter().any(|column| column.name == actual.name) {
+            differences.push(SchemaDifference::ExtraColumn {
+                table: table.to_owned(),
+                column: actual.name.clone(),
+            });
+        }
+    }
+
+    differences
+}
@@ -0,0 +1 @@
+CREATE TABLE posts (id integer primary key, user_id integer not null references users(id));
@@ -1,4 +1,5 @@
 extern crate nomad;
+extern crate nomad_files;
 
 macro_rules! driver_tests {
     ($driver:expr) => {
@@ -7,11 +8,15 @@ macro_rules! driver_tests {
             let migrations = [
                 ::nomad::Migration {
                     version: 1,
-                    sql: "-- test migration 1",
+                    name: ::std::borrow::Cow::Borrowed("test_migration_1"),
+                    sql: ::std::borrow::Cow::Borrowed("-- test migration 1"),
+                    down: None,
                 },
                 ::nomad::Migration {
                     version: 2,
-                    sql: "-- test migration 2",
+                    name: ::std::borrow::Cow::Borrowed("test_migration_2"),
+                    sql: ::std::borrow::Cow::Borrowed("-- test migration 2"),
+                    down: None,
                 },
             ]
             .into();
@@ -65,11 +70,15 @@ macro_rules! driver_tests {
             let migrations = [
                 ::nomad::Migration {
                     version: 1,
-                    sql: "-- test migration 1",
+                    name: ::std::borrow::Cow::Borrowed("test_migration_1"),
+                    sql: ::std::borrow::Cow::Borrowed("-- test migration 1"),
+                    down: None,
                 },
                 ::nomad::Migration {
                     version: 2,
-                    sql: "-- test migration 2",
+                    name: ::std::borrow::Cow::Borrowed("test_migration_2"),
+                    sql: ::std::borrow::Cow::Borrowed("-- test migration 2"),
+                    down: None,
                 },
             ];
 
@@ -82,7 +91,9 @@ macro_rules! driver_tests {
 
             let migrations = [::nomad::Migration {
                 version: 1,
-                sql: "-- test migration 1",
+                name: ::std::borrow::Cow::Borrowed("test_migration_1"),
+                sql: ::std::borrow::Cow::Borrowed("-- test migration 1"),
+                down: None,
             }];
 
             match ::nomad::MigrationRunner::new(&mut driver).migrate(migrations) {
@@ -91,129 +102,2754 @@ macro_rules! driver_tests {
             }
         }
 
+        #[test]
+        fn duplicate_version() {
+            let migrations = [
+                ::nomad::Migration {
+                    version: 1,
+                    name: ::std::borrow::Cow::Borrowed("test_migration_1"),
+                    sql: ::std::borrow::Cow::Borrowed("-- test migration 1"),
+                    down: None,
+                },
+                ::nomad::Migration {
+                    version: 1,
+                    name: ::std::borrow::Cow::Borrowed("test_migration_1_from_another_branch"),
+                    sql: ::std::borrow::Cow::Borrowed("-- test migration 1 from another branch"),
+                    down: None,
+                },
+            ];
+
+            let mut driver = $driver;
+
+            match ::nomad::MigrationRunner::new(&mut driver).migrate(migrations) {
+                Err(::nomad::MigrationError::DuplicateVersion { version: 1, .. }) => {}
+                _ => panic!("Expected a DuplicateVersion error"),
+            }
+        }
+
+        #[test]
+        fn unapplied_version() {
+            use ::nomad::Driver;
+
+            let migrations = [
+                ::nomad::Migration {
+                    version: 20201019025309,
+                    name: ::std::borrow::Cow::Borrowed("test_migration_1"),
+                    sql: ::std::borrow::Cow::Borrowed("-- test migration 1"),
+                    down: None,
+                },
+                ::nomad::Migration {
+                    version: 20201021120000,
+                    name: ::std::borrow::Cow::Borrowed("test_migration_3"),
+                    sql: ::std::borrow::Cow::Borrowed("-- test migration 3"),
+                    down: None,
+                },
+            ];
+
+            let mut driver = $driver;
+
+            ::nomad::MigrationRunner::new(&mut driver)
+                .migrate(migrations.clone())
+                .expect("Migrations should succeed")
+                .expect("Migrations should be executed");
+
+            // A migration merged in from another branch, older than the latest applied
+            let migrations = [
+                migrations[0].clone(),
+                ::nomad::Migration {
+                    version: 20201020090000,
+                    name: ::std::borrow::Cow::Borrowed("test_migration_2"),
+                    sql: ::std::borrow::Cow::Borrowed("-- test migration 2"),
+                    down: None,
+                },
+                migrations[1].clone(),
+            ];
+
+            match ::nomad::MigrationRunner::new(&mut driver).migrate(migrations) {
+                Err(::nomad::MigrationError::UnappliedVersion {
+                    version: 20201020090000,
+                    ..
+                }) => {}
+                _ => panic!("Expected an UnappliedVersion error"),
+            }
+
+            assert_eq!(
+                driver
+                    .applied_migrations(&Default::default(), ::nomad::DEFAULT_NAMESPACE)
+                    .expect("Should be able to get applied migrations")
+                    .iter()
+                    .map(|migration| migration.version)
+                    .collect::<Vec<u64>>(),
+                [20201019025309, 20201021120000]
+            );
+        }
+
+        #[test]
+        fn out_of_order() {
+            use ::nomad::Driver;
+
+            let migrations = [
+                ::nomad::Migration {
+                    version: 1,
+                    name: ::std::borrow::Cow::Borrowed("test_migration_1"),
+                    sql: ::std::borrow::Cow::Borrowed("-- test migration 1"),
+                    down: None,
+                },
+                ::nomad::Migration {
+                    version: 3,
+                    name: ::std::borrow::Cow::Borrowed("test_migration_3"),
+                    sql: ::std::borrow::Cow::Borrowed("-- test migration 3"),
+                    down: None,
+                },
+            ];
+
+            let mut driver = $driver;
+
+            ::nomad::MigrationRunner::new(&mut driver)
+                .migrate(migrations.clone())
+                .expect("Migrations should succeed")
+                .expect("Migrations should be executed");
+
+            let migrations = [
+                migrations[0].clone(),
+                ::nomad::Migration {
+                    version: 2,
+                    name: ::std::borrow::Cow::Borrowed("hotfix_migration_2"),
+                    sql: ::std::borrow::Cow::Borrowed("-- hotfix migration 2"),
+                    down: None,
+                },
+                migrations[1].clone(),
+            ];
+
+            assert_eq!(
+                ::nomad::MigrationRunner::new(&mut driver)
+                    .with_order_policy(::nomad::OrderPolicy::OutOfOrder)
+                    .migrate(migrations.clone())
+                    .expect("Migrations should succeed"),
+                Some(2)
+            );
+
+            assert_eq!(
+                driver
+                    .latest_version(&Default::default(), ::nomad::DEFAULT_NAMESPACE)
+                    .expect("Should be able to get a version"),
+                Some(3)
+            );
+
+            assert_eq!(
+                driver
+                    .applied_migrations(&Default::default(), ::nomad::DEFAULT_NAMESPACE)
+                    .expect("Should be able to get applied migrations"),
+                [
+                    ::nomad::AppliedMigration {
+                        version: 1,
+                        name: "test_migration_1".to_owned(),
+                        out_of_order: false,
+                        checksum: Some(migrations[0].checksum()),
+                    },
+                    ::nomad::AppliedMigration {
+                        version: 2,
+                        name: "hotfix_migration_2".to_owned(),
+                        out_of_order: true,
+                        checksum: Some(migrations[1].checksum()),
+                    },
+                    ::nomad::AppliedMigration {
+                        version: 3,
+                        name: "test_migration_3".to_owned(),
+                        out_of_order: false,
+                        checksum: Some(migrations[2].checksum()),
+                    },
+                ]
+            );
+        }
+
+        #[test]
+        fn legacy_history() {
+            use ::nomad::{Driver, Transaction};
+
+            let migrations = [
+                ::nomad::Migration {
+                    version: 1,
+                    name: ::std::borrow::Cow::Borrowed("test_migration_1"),
+                    sql: ::std::borrow::Cow::Borrowed("-- test migration 1"),
+                    down: None,
+                },
+                ::nomad::Migration {
+                    version: 2,
+                    name: ::std::borrow::Cow::Borrowed("test_migration_2"),
+                    sql: ::std::borrow::Cow::Borrowed("-- test migration 2"),
+                    down: None,
+                },
+                ::nomad::Migration {
+                    version: 3,
+                    name: ::std::borrow::Cow::Borrowed("test_migration_3"),
+                    sql: ::std::borrow::Cow::Borrowed("-- test migration 3"),
+                    down: None,
+                },
+            ];
+
+            let mut driver = $driver;
+
+            // Simulate a database migrated before per-version history was tracked
+            let mut txn = ::nomad::Driver::begin(&mut driver).expect("Should be able to begin");
+            txn.push_latest_version(&Default::default(), ::nomad::DEFAULT_NAMESPACE, 2)
+                .expect("Should be able to push a version");
+            ::nomad::Transaction::commit(txn).expect("Should be able to commit");
+
+            assert_eq!(
+                ::nomad::MigrationRunner::new(&mut driver)
+                    .migrate(migrations)
+                    .expect("Migrations should succeed"),
+                Some(3)
+            );
+
+            assert_eq!(
+                driver
+                    .applied_migrations(&Default::default(), ::nomad::DEFAULT_NAMESPACE)
+                    .expect("Should be able to get applied migrations")
+                    .iter()
+                    .map(|migration| migration.version)
+                    .collect::<Vec<u64>>(),
+                [1, 2, 3]
+            );
+        }
+
+        #[test]
+        fn placeholders() {
+            use ::nomad::Driver;
+
+            let migrations = [::nomad::Migration {
+                version: 1,
+                name: ::std::borrow::Cow::Borrowed("create_accounts"),
+                sql: ::std::borrow::Cow::Borrowed(
+                    "-- create ${prefix}accounts in ${schema}, not $${literal}",
+                ),
+                down: None,
+            }];
+
+            // Without opting in, the SQL runs as written
+            let mut unrendered = $driver;
+            assert_eq!(
+                ::nomad::MigrationRunner::new(&mut unrendered)
+                    .migrate(migrations.clone())
+                    .expect("Migrations should succeed"),
+                Some(1)
+            );
+            assert_eq!(
+                unrendered
+                    .applied_migrations(&Default::default(), ::nomad::DEFAULT_NAMESPACE)
+                    .expect("Should be able to get applied migrations")
+                    .iter()
+                    .map(|migration| migration.checksum)
+                    .collect::<Vec<_>>(),
+                [Some(migrations[0].checksum())]
+            );
+
+            let mut driver = $driver;
+
+            // Opting in without values still rejects every placeholder
+            match ::nomad::MigrationRunner::new(&mut driver)
+                .with_placeholders(Vec::<(&str, &str)>::new())
+                .migrate(migrations.clone())
+            {
+                Err(::nomad::MigrationError::UnknownPlaceholder {
+                    version: 1,
+                    ref placeholder,
+                    ..
+                }) if placeholder == "prefix" => {}
+                _ => panic!("Expected an UnknownPlaceholder error"),
+            }
+
+            match ::nomad::MigrationRunner::new(&mut driver)
+                .with_placeholders(vec![("prefix", "tenant_a_")])
+                .migrate(migrations.clone())
+            {
+                Err(::nomad::MigrationError::UnknownPlaceholder {
+                    version: 1,
+                    ref placeholder,
+                    ..
+                }) if placeholder == "schema" => {}
+                _ => panic!("Expected an UnknownPlaceholder error"),
+            }
+
+            assert_eq!(
+                ::nomad::MigrationRunner::new(&mut driver)
+                    .with_placeholders(vec![("prefix", "tenant_a_"), ("schema", "main")])
+                    .migrate(migrations)
+                    .expect("Migrations should succeed"),
+                Some(1)
+            );
+
+            // The rendered SQL is what gets checksummed
+            let rendered = ::nomad::Migration::new(
+                1,
+                "create_accounts",
+                "-- create tenant_a_accounts in main, not ${literal}",
+            );
+            assert_eq!(
+                driver
+                    .applied_migrations(&Default::default(), ::nomad::DEFAULT_NAMESPACE)
+                    .expect("Should be able to get applied migrations")
+                    .iter()
+                    .map(|migration| migration.checksum)
+                    .collect::<Vec<_>>(),
+                [Some(rendered.checksum())]
+            );
+        }
+
+        #[test]
+        fn registry_migrate_all() {
+            use ::nomad::Driver;
+
+            let core = [
+                ::nomad::Migration {
+                    version: 1,
+                    name: ::std::borrow::Cow::Borrowed("core_migration_1"),
+                    sql: ::std::borrow::Cow::Borrowed("-- core migration 1"),
+                    down: None,
+                },
+                ::nomad::Migration {
+                    version: 2,
+                    name: ::std::borrow::Cow::Borrowed("core_migration_2"),
+                    sql: ::std::borrow::Cow::Borrowed("-- core migration 2"),
+                    down: None,
+                },
+            ];
+            let billing = [::nomad::Migration {
+                version: 1,
+                name: ::std::borrow::Cow::Borrowed("billing_migration_1"),
+                sql: ::std::borrow::Cow::Borrowed("-- billing migration 1"),
+                down: None,
+            }];
+
+            let mut registry = ::nomad::MigrationRegistry::new();
+            registry
+                .register("core", core)
+                .and_then(|registry| registry.register("billing", billing))
+                .expect("Namespaces should be unique");
+
+            let mut driver = $driver;
+
+            let reports = registry
+                .migrate_all(&mut driver)
+                .expect("Migrations should succeed");
+            assert_eq!(
+                reports
+                    .iter()
+                    .map(|report| (report.namespace, report.last_executed()))
+                    .collect::<Vec<_>>(),
+                [("core", Some(2)), ("billing", Some(1))]
+            );
+            assert_eq!(reports[0].applied[1].to_string(), "2 core_migration_2");
+
+            let reports = registry
+                .migrate_all(&mut driver)
+                .expect("Migrations should succeed");
+            assert!(reports.iter().all(|report| report.applied.is_empty()));
+
+            assert_eq!(
+                driver
+                    .latest_version(&Default::default(), "billing")
+                    .expect("Should be able to get a version"),
+                Some(1)
+            );
+        }
+
+        #[test]
+        fn registry_rollback() {
+            use ::nomad::Driver;
+
+            let mut registry = ::nomad::MigrationRegistry::new();
+            registry
+                .register(
+                    "core",
+                    [::nomad::Migration {
+                        version: 1,
+                        name: ::std::borrow::Cow::Borrowed("core_migration_1"),
+                        sql: ::std::borrow::Cow::Borrowed("-- core migration 1"),
+                        down: None,
+                    }],
+                )
+                .and_then(|registry| {
+                    registry.register(
+                        "billing",
+                        [::nomad::Migration {
+                            version: 1,
+                            name: ::std::borrow::Cow::Borrowed("evil"),
+                            sql: ::std::borrow::Cow::Borrowed("evil"),
+                            down: None,
+                        }],
+                    )
+                })
+                .expect("Namespaces should be unique");
+
+            let mut driver = $driver;
+
+            if let Ok(_) = registry.migrate_all(&mut driver) {
+                panic!("Migrations should fail");
+            }
+
+            assert_eq!(
+                driver
+                    .latest_version(&Default::default(), "core")
+                    .expect("Should be able to get a version"),
+                None
+            );
+        }
+
+        #[test]
+        fn tracking_table() {
+            use ::nomad::Driver;
+
+            let migrations = [::nomad::Migration {
+                version: 1,
+                name: ::std::borrow::Cow::Borrowed("test_migration_1"),
+                sql: ::std::borrow::Cow::Borrowed("-- test migration 1"),
+                down: None,
+            }];
+
+            let table_name = String::from("_schema_history");
+            let table = ::nomad::TrackingTable::new(&table_name);
+
+            let mut driver = $driver;
+
+            ::nomad::MigrationRunner::new(&mut driver)
+                .with_tracking_table(table)
+                .migrate(migrations)
+                .expect("Migrations should succeed")
+                .expect("Migrations should be executed");
+
+            assert_eq!(
+                driver
+                    .latest_version(&table, ::nomad::DEFAULT_NAMESPACE)
+                    .expect("Should be able to get a version"),
+                Some(1)
+            );
+
+            assert_eq!(
+                driver
+                    .latest_version(&Default::default(), ::nomad::DEFAULT_NAMESPACE)
+                    .expect("Should be able to get a version"),
+                None
+            );
+        }
+
+        #[test]
+        fn execution_error() {
+            let migrations = [::nomad::Migration {
+                version: 1,
+                name: ::std::borrow::Cow::Borrowed("evil"),
+                sql: ::std::borrow::Cow::Borrowed("evil"),
+                down: None,
+            }];
+
+            let mut driver = $driver;
+
+            match ::nomad::MigrationRunner::new(&mut driver).migrate(migrations) {
+                Err(::nomad::MigrationError::ExecutionError {
+                    version: 1,
+                    ref name,
+                    ..
+                }) if name == "evil" => {}
+                _ => panic!("Expected an ExecutionError"),
+            }
+        }
+
         #[test]
         fn rollback() {
             use ::nomad::Driver;
 
-            let migrations = [
-                ::nomad::Migration {
-                    version: 1,
-                    sql: "-- test migration 1",
-                },
-                ::nomad::Migration {
-                    version: 2,
-                    sql: "evil",
-                },
-                ::nomad::Migration {
-                    version: 2,
-                    sql: "-- test migration 2",
-                },
-            ];
+            let migrations = [
+                ::nomad::Migration {
+                    version: 1,
+                    name: ::std::borrow::Cow::Borrowed("test_migration_1"),
+                    sql: ::std::borrow::Cow::Borrowed("-- test migration 1"),
+                    down: None,
+                },
+                ::nomad::Migration {
+                    version: 2,
+                    name: ::std::borrow::Cow::Borrowed("test_migration_2"),
+                    sql: ::std::borrow::Cow::Borrowed("-- test migration 2"),
+                    down: None,
+                },
+                ::nomad::Migration {
+                    version: 3,
+                    name: ::std::borrow::Cow::Borrowed("evil"),
+                    sql: ::std::borrow::Cow::Borrowed("evil"),
+                    down: None,
+                },
+            ];
+
+            let mut driver = $driver;
+
+            // The failing statement comes after two that succeeded, which must be rolled back with it
+            match ::nomad::MigrationRunner::new(&mut driver).migrate(migrations) {
+                Err(::nomad::MigrationError::ExecutionError { version: 3, .. }) => {}
+                result => panic!("Expected an execution error, got {:?}", result),
+            }
+
+            assert_eq!(
+                driver
+                    .latest_version(&Default::default(), ::nomad::DEFAULT_NAMESPACE)
+                    .expect("Should be able to get a version"),
+                None
+            );
+        }
+
+        #[test]
+        fn down_migrations() {
+            use ::nomad::Driver;
+
+            let migrations = [
+                ::nomad::Migration {
+                    version: 1,
+                    name: ::std::borrow::Cow::Borrowed("test_migration_1"),
+                    sql: ::std::borrow::Cow::Borrowed("-- test migration 1"),
+                    down: Some(::std::borrow::Cow::Borrowed("-- revert test migration 1")),
+                },
+                ::nomad::Migration {
+                    version: 2,
+                    name: ::std::borrow::Cow::Borrowed("test_migration_2"),
+                    sql: ::std::borrow::Cow::Borrowed("-- test migration 2"),
+                    down: None,
+                },
+                ::nomad::Migration {
+                    version: 3,
+                    name: ::std::borrow::Cow::Borrowed("test_migration_3"),
+                    sql: ::std::borrow::Cow::Borrowed("-- test migration 3"),
+                    down: Some(::std::borrow::Cow::Borrowed("-- revert test migration 3")),
+                },
+            ];
+
+            let mut driver = $driver;
+
+            ::nomad::MigrationRunner::new(&mut driver)
+                .migrate(migrations.clone())
+                .expect("Migrations should succeed");
+
+            let reverted = ::nomad::MigrationRunner::new(&mut driver)
+                .rollback(migrations.clone(), Some(2))
+                .expect("Rollback should succeed");
+            assert_eq!(
+                reverted
+                    .iter()
+                    .map(|migration| migration.version)
+                    .collect::<Vec<_>>(),
+                vec![3]
+            );
+            assert_eq!(
+                driver
+                    .latest_version(&Default::default(), ::nomad::DEFAULT_NAMESPACE)
+                    .expect("Should be able to get a version"),
+                Some(2)
+            );
+
+            match ::nomad::MigrationRunner::new(&mut driver).rollback(migrations.clone(), None) {
+                Err(::nomad::MigrationError::IrreversibleMigration { version: 2, .. }) => {}
+                result => panic!("Expected an irreversible migration error, got {:?}", result),
+            }
+            assert_eq!(
+                driver
+                    .applied_migrations(&Default::default(), ::nomad::DEFAULT_NAMESPACE)
+                    .expect("Should be able to read the history")
+                    .len(),
+                2
+            );
+
+            assert_eq!(
+                ::nomad::MigrationRunner::new(&mut driver)
+                    .migrate(migrations)
+                    .expect("Migrations should succeed"),
+                Some(3)
+            );
+        }
+    };
+}
+
+mod dummy {
+    use std::collections::HashMap;
+    use std::fmt::Display;
+
+    #[derive(Debug)]
+    struct DummyError();
+
+    impl Display for DummyError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self)
+        }
+    }
+
+    impl std::error::Error for DummyError {}
+
+    // Namespaces are tracked separately for each tracking table
+    fn tracking_key(table: &::nomad::TrackingTable<'_>, namespace: &str) -> String {
+        format!("{:?}.{}/{}", table.schema, table.name, namespace)
+    }
+
+    #[derive(Default)]
+    struct DummyDriver {
+        latest_versions: HashMap<String, u64>,
+        applied_migrations: HashMap<String, Vec<::nomad::AppliedMigration>>,
+    }
+
+    struct DummyTransaction<'a> {
+        driver: &'a mut DummyDriver,
+        changes: HashMap<String, Option<u64>>,
+        applied: Vec<(String, ::nomad::AppliedMigration)>,
+        removed: Vec<(String, u64)>,
+    }
+
+    impl ::nomad::Transaction<'_, DummyDriver> for DummyTransaction<'_> {
+        fn commit(self) -> Result<(), DummyError> {
+            for (k, v) in self.changes {
+                match v {
+                    Some(v) => self.driver.latest_versions.insert(k, v),
+                    None => self.driver.latest_versions.remove(&k),
+                };
+            }
+
+            for (k, v) in self.applied {
+                let migrations = self.driver.applied_migrations.entry(k).or_default();
+                migrations.push(v);
+                migrations.sort_by_key(|migration| migration.version);
+            }
+
+            for (k, version) in self.removed {
+                if let Some(migrations) = self.driver.applied_migrations.get_mut(&k) {
+                    migrations.retain(|migration| migration.version != version);
+                }
+            }
+
+            Ok(())
+        }
+
+        fn execute_sql(&mut self, sql: &str) -> Result<(), DummyError> {
+            if sql == "evil" {
+                Err(DummyError())
+            } else {
+                Ok(())
+            }
+        }
+
+        fn push_latest_version(
+            &mut self,
+            table: &::nomad::TrackingTable<'_>,
+            namespace: &str,
+            version: u64,
+        ) -> Result<(), DummyError> {
+            self.changes
+                .insert(tracking_key(table, namespace), Some(version));
+
+            Ok(())
+        }
+
+        fn record_applied(
+            &mut self,
+            table: &::nomad::TrackingTable<'_>,
+            namespace: &str,
+            migration: &::nomad::AppliedMigration,
+        ) -> Result<(), DummyError> {
+            self.applied
+                .push((tracking_key(table, namespace), migration.clone()));
+
+            Ok(())
+        }
+
+        fn remove_applied(
+            &mut self,
+            table: &::nomad::TrackingTable<'_>,
+            namespace: &str,
+            version: u64,
+            latest_version: Option<u64>,
+        ) -> Result<(), DummyError> {
+            self.removed.push((tracking_key(table, namespace), version));
+            self.changes
+                .insert(tracking_key(table, namespace), latest_version);
+
+            Ok(())
+        }
+    }
+
+    impl<'a> ::nomad::Driver<'a> for DummyDriver {
+        type Transaction = DummyTransaction<'a>;
+        type Error = DummyError;
+
+        fn begin(&'a mut self) -> Result<Self::Transaction, Self::Error> {
+            Ok(DummyTransaction {
+                driver: self,
+                changes: Default::default(),
+                applied: Default::default(),
+                removed: Default::default(),
+            })
+        }
+
+        fn latest_version(
+            &mut self,
+            table: &::nomad::TrackingTable<'_>,
+            namespace: &str,
+        ) -> Result<Option<u64>, Self::Error> {
+            Ok(self
+                .latest_versions
+                .get(&tracking_key(table, namespace))
+                .map(|n| *n))
+        }
+
+        fn applied_migrations(
+            &mut self,
+            table: &::nomad::TrackingTable<'_>,
+            namespace: &str,
+        ) -> Result<Vec<::nomad::AppliedMigration>, Self::Error> {
+            Ok(self
+                .applied_migrations
+                .get(&tracking_key(table, namespace))
+                .cloned()
+                .unwrap_or_default())
+        }
+
+        fn query_rows(&mut self, _sql: &str) -> Result<Vec<Vec<Option<String>>>, Self::Error> {
+            Ok(Vec::new())
+        }
+    }
+
+    driver_tests!(DummyDriver::default());
+}
+
+#[cfg(feature = "sqlx")]
+mod sqlx {
+    #[cfg(feature = "sqlx-sqlite")]
+    mod sqlite {
+        use ::sqlx::sqlite::SqliteConnection;
+        use ::sqlx::Connection;
+        use futures::executor::block_on;
+
+        driver_tests!(block_on(SqliteConnection::connect("sqlite::memory:"))
+            .expect("Falied to open an in-memory SQLite database"));
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+mod rusqlite {
+    use ::rusqlite::{Connection, NO_PARAMS};
+
+    driver_tests!(
+        Connection::open_in_memory().expect("Failed to open an in-memory SQLite database")
+    );
+
+    #[test]
+    fn upgrade_tracking_layout() {
+        use ::nomad::Driver;
+        use ::rusqlite::params;
+
+        let migrations = [
+            ::nomad::Migration {
+                version: 1,
+                name: ::std::borrow::Cow::Borrowed("test_migration_1"),
+                sql: ::std::borrow::Cow::Borrowed("-- test migration 1"),
+                down: None,
+            },
+            ::nomad::Migration {
+                version: 20201019025309,
+                name: ::std::borrow::Cow::Borrowed("test_migration_2"),
+                sql: ::std::borrow::Cow::Borrowed("-- test migration 2"),
+                down: None,
+            },
+            ::nomad::Migration {
+                version: 20201020000000,
+                name: ::std::borrow::Cow::Borrowed("test_migration_3"),
+                sql: ::std::borrow::Cow::Borrowed("-- test migration 3"),
+                down: None,
+            },
+        ];
+
+        let mut conn =
+            Connection::open_in_memory().expect("Failed to open an in-memory SQLite database");
+
+        // The tracking table as laid out by the first release of nomad
+        conn.execute_batch(
+            "CREATE TABLE nomad_migrations (namespace text not null primary key, version blob not null) WITHOUT ROWID;",
+        )
+        .expect("Failed to create the original tracking table");
+        conn.execute(
+            "INSERT INTO nomad_migrations(namespace, version) VALUES(?, ?)",
+            params!["nomad", &20201019025309u64.to_le_bytes()[..]],
+        )
+        .expect("Failed to insert the original tracking data");
+
+        assert_eq!(
+            conn.latest_version(&Default::default(), ::nomad::DEFAULT_NAMESPACE)
+                .expect("Should be able to get a version"),
+            Some(20201019025309)
+        );
+
+        let version_type: String = conn
+            .query_row(
+                "SELECT typeof(version) FROM nomad_migrations",
+                NO_PARAMS,
+                |row| row.get(0),
+            )
+            .expect("Should be able to read the upgraded table");
+        assert_eq!(version_type, "integer");
+
+        assert_eq!(
+            ::nomad::MigrationRunner::new(&mut conn)
+                .migrate(migrations.clone())
+                .expect("Migrations should succeed"),
+            Some(20201020000000)
+        );
+
+        assert_eq!(
+            conn.applied_migrations(&Default::default(), ::nomad::DEFAULT_NAMESPACE)
+                .expect("Should be able to get applied migrations")
+                .iter()
+                .map(|migration| migration.checksum)
+                .collect::<Vec<_>>(),
+            // Only the migration nomad ran has a checksum, the backfilled ones were never seen running
+            vec![None, None, Some(migrations[2].checksum())]
+        );
+
+        // So backfilled migrations can still change without a checksum mismatch
+        let mut changed = migrations.clone();
+        changed[0].sql = ::std::borrow::Cow::Borrowed("-- test migration 1, reworded");
+        assert_eq!(
+            ::nomad::MigrationRunner::new(&mut conn)
+                .migrate(changed)
+                .expect("Migrations should succeed"),
+            None
+        );
+    }
+
+    #[test]
+    fn checksum_mismatch() {
+        let mut migrations = vec![
+            ::nomad::Migration::new(1, "create_users", "CREATE TABLE users (id integer);"),
+            ::nomad::Migration::new(2, "create_posts", "CREATE TABLE posts (id integer);"),
+        ];
+
+        let mut conn =
+            Connection::open_in_memory().expect("Failed to open an in-memory SQLite database");
+        ::nomad::MigrationRunner::new(&mut conn)
+            .migrate(migrations.clone())
+            .expect("Migrations should succeed");
+
+        let applied = migrations[0].checksum();
+        migrations[0].sql = "CREATE TABLE users (id integer, email text);".into();
+        migrations.push(::nomad::Migration::new(
+            3,
+            "create_tags",
+            "CREATE TABLE tags (id integer);",
+        ));
+
+        match ::nomad::MigrationRunner::new(&mut conn)
+            .with_verify_checksums(true)
+            .migrate(migrations.clone())
+        {
+            Err(::nomad::MigrationError::ChecksumMismatch {
+                version: 1,
+                applied: stored,
+                current,
+                ..
+            }) => {
+                assert_eq!(stored, applied);
+                assert_eq!(current, migrations[0].checksum());
+            }
+            result => panic!("Expected a checksum mismatch, got {:?}", result),
+        }
+
+        let tags = |conn: &Connection| {
+            conn.query_row(
+                "SELECT name FROM sqlite_master WHERE name = 'tags'",
+                NO_PARAMS,
+                |row| row.get::<_, String>(0),
+            )
+            .is_ok()
+        };
+
+        // Nothing newer was applied past the changed migration
+        assert!(!tags(&conn));
+
+        // Checksums are only compared when asked for
+        assert_eq!(
+            ::nomad::MigrationRunner::new(&mut conn)
+                .migrate(migrations)
+                .expect("Migrations should succeed"),
+            Some(3)
+        );
+        assert!(tags(&conn));
+    }
+
+    #[test]
+    fn checksum() {
+        // The standard CRC-32 check value
+        assert_eq!(
+            ::nomad::Migration::new(1, "check", "123456789").checksum(),
+            0xCBF4_3926
+        );
+    }
+
+    #[test]
+    fn tenant_placeholders() {
+        let migrations = [::nomad::Migration::new(
+            1,
+            "create_accounts",
+            "CREATE TABLE ${prefix}accounts (id integer primary key);",
+        )
+        .with_down("DROP TABLE ${prefix}accounts;")];
+
+        let mut conn =
+            Connection::open_in_memory().expect("Failed to open an in-memory SQLite database");
+
+        for (namespace, prefix) in &[("tenant_a", "a_"), ("tenant_b", "b_")] {
+            ::nomad::MigrationRunner::with_namespace(&mut conn, namespace)
+                .with_placeholders(vec![("prefix", *prefix)])
+                .migrate(migrations.clone())
+                .expect("Migrations should succeed");
+        }
+
+        let tables = |conn: &Connection| -> Vec<String> {
+            let mut statement = conn
+                .prepare("SELECT name FROM sqlite_master WHERE name LIKE '%accounts' ORDER BY name")
+                .expect("Should be able to list tables");
+            let tables = statement
+                .query_map(NO_PARAMS, |row| row.get(0))
+                .expect("Should be able to list tables")
+                .collect::<Result<_, _>>()
+                .expect("Should be able to read table names");
+            tables
+        };
+        assert_eq!(tables(&conn), ["a_accounts", "b_accounts"]);
+
+        ::nomad::MigrationRunner::with_namespace(&mut conn, "tenant_b")
+            .with_placeholders(vec![("prefix", "b_")])
+            .rollback(migrations, None)
+            .expect("Rollback should succeed");
+        assert_eq!(tables(&conn), ["a_accounts"]);
+    }
+
+    #[test]
+    fn registry_placeholders() {
+        let users = [::nomad::Migration::new(
+            1,
+            "create_users",
+            "CREATE TABLE ${prefix}users (id integer primary key);",
+        )];
+        let posts = [::nomad::Migration::new(
+            1,
+            "create_posts",
+            "CREATE TABLE ${prefix}posts (id integer primary key);",
+        )];
+
+        let mut conn =
+            Connection::open_in_memory().expect("Failed to open an in-memory SQLite database");
+
+        let mut registry = ::nomad::MigrationRegistry::new();
+        registry
+            .register("users", users)
+            .and_then(|registry| registry.register("posts", posts))
+            .expect("Namespaces should be unique")
+            .with_placeholders(vec![("schema", "main")]);
+
+        // Without a value no namespace is migrated
+        match registry.migrate_all(&mut conn) {
+            Err(::nomad::MigrationError::UnknownPlaceholder { placeholder, .. }) => {
+                assert_eq!(placeholder, "prefix")
+            }
+            result => panic!("Expected an unknown placeholder error, got {:?}", result),
+        }
+
+        registry.with_placeholders(vec![("prefix", "app_")]);
+        registry
+            .migrate_all(&mut conn)
+            .expect("Migrations should succeed");
+
+        let tables: i64 = conn
+            .query_row(
+                "SELECT count(*) FROM sqlite_master WHERE name IN ('app_users', 'app_posts')",
+                NO_PARAMS,
+                |row| row.get(0),
+            )
+            .expect("Should be able to list tables");
+        assert_eq!(tables, 2);
+    }
+
+    #[test]
+    fn tracking_schema() {
+        let migrations = [::nomad::Migration {
+            version: 1,
+            name: ::std::borrow::Cow::Borrowed("create_users"),
+            sql: ::std::borrow::Cow::Borrowed("CREATE TABLE users (id integer primary key);"),
+            down: None,
+        }];
+
+        let mut conn =
+            Connection::open_in_memory().expect("Failed to open an in-memory SQLite database");
+        conn.execute("ATTACH DATABASE ':memory:' AS ops", NO_PARAMS)
+            .expect("Failed to attach a database");
+
+        ::nomad::MigrationRunner::new(&mut conn)
+            .with_tracking_table(::nomad::TrackingTable::new("_schema_history").with_schema("ops"))
+            .migrate(migrations)
+            .expect("Migrations should succeed")
+            .expect("Migrations should be executed");
+
+        let tracked: i64 = conn
+            .query_row(
+                "SELECT count(*) FROM ops._schema_history_history",
+                NO_PARAMS,
+                |row| row.get(0),
+            )
+            .expect("The history table should be in the attached database");
+        assert_eq!(tracked, 1);
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+mod import {
+    use ::nomad::{Driver, ImportSource, Migration, MigrationError, MigrationRunner};
+    use ::rusqlite::{Connection, NO_PARAMS};
+
+    const MIGRATIONS: [Migration; 3] = [
+        Migration {
+            version: 20201019025309,
+            name: ::std::borrow::Cow::Borrowed("create_users"),
+            sql: ::std::borrow::Cow::Borrowed("-- create users"),
+            down: None,
+        },
+        Migration {
+            version: 20201020090000,
+            name: ::std::borrow::Cow::Borrowed("add_user_email"),
+            sql: ::std::borrow::Cow::Borrowed("-- add user email"),
+            down: None,
+        },
+        Migration {
+            version: 20201021120000,
+            name: ::std::borrow::Cow::Borrowed("create_posts"),
+            sql: ::std::borrow::Cow::Borrowed("-- create posts"),
+            down: None,
+        },
+    ];
+
+    fn open(setup: &str) -> Connection {
+        let conn =
+            Connection::open_in_memory().expect("Failed to open an in-memory SQLite database");
+        conn.execute_batch(setup)
+            .expect("Failed to create the foreign tracking table");
+        conn
+    }
+
+    #[test]
+    fn sqlx() {
+        let mut conn = open(
+            r#"
+            CREATE TABLE _sqlx_migrations (
+                version BIGINT PRIMARY KEY,
+                description TEXT NOT NULL,
+                installed_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                success BOOLEAN NOT NULL,
+                checksum BLOB NOT NULL,
+                execution_time BIGINT NOT NULL
+            );
+            INSERT INTO _sqlx_migrations(version, description, success, checksum, execution_time)
+                VALUES (20201019025309, 'create users', TRUE, x'00', 1),
+                       (20201020090000, 'add user email', TRUE, x'00', 1);
+            "#,
+        );
+
+        let imported = MigrationRunner::new(&mut conn)
+            .import(ImportSource::Sqlx, MIGRATIONS)
+            .expect("Import should succeed");
+        assert_eq!(
+            imported
+                .iter()
+                .map(|migration| migration.to_string())
+                .collect::<Vec<_>>(),
+            [
+                "20201019025309 create_users",
+                "20201020090000 add_user_email"
+            ]
+        );
+
+        assert_eq!(
+            MigrationRunner::new(&mut conn)
+                .migrate(MIGRATIONS)
+                .expect("Migrations should succeed"),
+            Some(20201021120000)
+        );
+
+        match MigrationRunner::new(&mut conn).import(ImportSource::Sqlx, MIGRATIONS) {
+            Err(MigrationError::AlreadyTracked { .. }) => {}
+            _ => panic!("Expected an AlreadyTracked error"),
+        }
+    }
+
+    #[test]
+    fn duplicate_version() {
+        let mut conn = open(
+            r#"
+            CREATE TABLE _sqlx_migrations (version BIGINT PRIMARY KEY, success BOOLEAN NOT NULL);
+            INSERT INTO _sqlx_migrations(version, success) VALUES (20201019025309, TRUE);
+            "#,
+        );
+
+        let migrations = [
+            MIGRATIONS[0].clone(),
+            Migration::new(20201019025309, "create_accounts", "-- create accounts"),
+        ];
+
+        match MigrationRunner::new(&mut conn).import(ImportSource::Sqlx, migrations) {
+            Err(MigrationError::DuplicateVersion {
+                version: 20201019025309,
+                ..
+            }) => {}
+            _ => panic!("Expected a DuplicateVersion error"),
+        }
+
+        assert_eq!(
+            conn.latest_version(&Default::default(), ::nomad::DEFAULT_NAMESPACE)
+                .expect("Should be able to get a version"),
+            None
+        );
+    }
+
+    #[test]
+    fn sqlx_failed_migration() {
+        let mut conn = open(
+            r#"
+            CREATE TABLE _sqlx_migrations (version BIGINT PRIMARY KEY, success BOOLEAN NOT NULL);
+            INSERT INTO _sqlx_migrations(version, success) VALUES (20201019025309, FALSE);
+            "#,
+        );
+
+        match MigrationRunner::new(&mut conn).import(ImportSource::Sqlx, MIGRATIONS) {
+            Err(MigrationError::InvalidImport { .. }) => {}
+            _ => panic!("Expected an InvalidImport error"),
+        }
+
+        assert_eq!(
+            conn.latest_version(&Default::default(), ::nomad::DEFAULT_NAMESPACE)
+                .expect("Should be able to get a version"),
+            None
+        );
+    }
+
+    #[test]
+    fn refinery() {
+        let mut conn = open(
+            r#"
+            CREATE TABLE refinery_schema_history (
+                version INT4 PRIMARY KEY,
+                name VARCHAR(255),
+                applied_on VARCHAR(255),
+                checksum VARCHAR(255)
+            );
+            INSERT INTO refinery_schema_history(version, name) VALUES (20201021120000, 'create_posts');
+            "#,
+        );
+
+        // The earlier migrations were never applied
+        match MigrationRunner::new(&mut conn).import(ImportSource::Refinery, MIGRATIONS) {
+            Err(MigrationError::UnappliedVersion {
+                version: 20201019025309,
+                ..
+            }) => {}
+            _ => panic!("Expected an UnappliedVersion error"),
+        }
+
+        conn.execute(
+            "INSERT INTO refinery_schema_history(version, name) VALUES (5, 'unknown')",
+            NO_PARAMS,
+        )
+        .expect("Failed to insert a foreign migration");
+
+        match MigrationRunner::new(&mut conn).import(ImportSource::Refinery, MIGRATIONS) {
+            Err(MigrationError::UnknownVersion { version: 5 }) => {}
+            _ => panic!("Expected an UnknownVersion error"),
+        }
+    }
+
+    #[test]
+    fn diesel() {
+        let migrations = ::nomad::nomad_migrations!("./tests/diesel");
+
+        let mut conn = open(
+            r#"
+            CREATE TABLE __diesel_schema_migrations (
+                version VARCHAR(50) PRIMARY KEY NOT NULL,
+                run_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );
+            INSERT INTO __diesel_schema_migrations(version) VALUES ('20201019025309');
+            CREATE TABLE users (id integer primary key);
+            "#,
+        );
+
+        let imported = MigrationRunner::new(&mut conn)
+            .import(ImportSource::Diesel, &migrations)
+            .expect("Import should succeed");
+        assert_eq!(
+            imported
+                .iter()
+                .map(|migration| migration.to_string())
+                .collect::<Vec<_>>(),
+            ["20201019025309 create_users"]
+        );
+
+        assert_eq!(
+            MigrationRunner::new(&mut conn)
+                .migrate(&migrations)
+                .expect("Migrations should succeed"),
+            Some(20201020090000)
+        );
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+mod script {
+    use ::nomad::script::{self, Dialect};
+    use ::nomad::{Driver, Migration, MigrationError, MigrationRunner};
+    use ::rusqlite::Connection;
+
+    const MIGRATIONS: [Migration; 3] = [
+        Migration {
+            version: 1,
+            name: ::std::borrow::Cow::Borrowed("create_users"),
+            sql: ::std::borrow::Cow::Borrowed("CREATE TABLE users (id integer primary key);"),
+            down: None,
+        },
+        Migration {
+            version: 2,
+            name: ::std::borrow::Cow::Borrowed("add_user_email"),
+            // Leaves off the final semicolon
+            sql: ::std::borrow::Cow::Borrowed(
+                "-- unique per user\nALTER TABLE users ADD COLUMN email text",
+            ),
+            down: None,
+        },
+        Migration {
+            version: 3,
+            name: ::std::borrow::Cow::Borrowed("create_posts"),
+            // Ends in a comment after the final semicolon
+            sql: ::std::borrow::Cow::Borrowed(
+                "CREATE TABLE posts (id integer primary key, title text);\n-- written by users",
+            ),
+            down: None,
+        },
+    ];
+
+    fn dump(conn: &mut Connection) -> Vec<Vec<Option<String>>> {
+        conn.query_rows("SELECT type, name, sql FROM sqlite_master ORDER BY type, name")
+            .expect("Should be able to read the schema")
+    }
+
+    #[test]
+    fn matches_migrate() {
+        let sql = script::render(MIGRATIONS, None, None, Dialect::Sqlite)
+            .expect("Rendering should succeed");
+        assert!(sql.starts_with("BEGIN;"));
+        assert!(sql.trim_end().ends_with("COMMIT;"));
+
+        let mut scripted =
+            Connection::open_in_memory().expect("Failed to open an in-memory SQLite database");
+        scripted.execute_batch(&sql).expect("The script should run");
+
+        let mut migrated =
+            Connection::open_in_memory().expect("Failed to open an in-memory SQLite database");
+        MigrationRunner::new(&mut migrated)
+            .migrate(MIGRATIONS)
+            .expect("Migrations should succeed");
+
+        assert_eq!(dump(&mut scripted), dump(&mut migrated));
+        assert_eq!(
+            scripted
+                .applied_migrations(&Default::default(), ::nomad::DEFAULT_NAMESPACE)
+                .expect("Should be able to read the history"),
+            migrated
+                .applied_migrations(&Default::default(), ::nomad::DEFAULT_NAMESPACE)
+                .expect("Should be able to read the history")
+        );
+        assert_eq!(
+            MigrationRunner::new(&mut scripted)
+                .migrate(MIGRATIONS)
+                .expect("Migrations should succeed"),
+            None
+        );
+    }
+
+    #[test]
+    fn version_range() {
+        let mut conn =
+            Connection::open_in_memory().expect("Failed to open an in-memory SQLite database");
+        MigrationRunner::new(&mut conn)
+            .migrate([MIGRATIONS[0].clone()])
+            .expect("Migrations should succeed");
+
+        let sql = script::render(MIGRATIONS, Some(1), Some(2), Dialect::Sqlite)
+            .expect("Rendering should succeed");
+        assert!(!sql.contains("CREATE TABLE users"));
+        assert!(!sql.contains("CREATE TABLE posts"));
+
+        conn.execute_batch(&sql).expect("The script should run");
+
+        assert_eq!(
+            conn.latest_version(&Default::default(), ::nomad::DEFAULT_NAMESPACE)
+                .expect("Should be able to get a version"),
+            Some(2)
+        );
+        assert_eq!(
+            MigrationRunner::new(&mut conn)
+                .migrate(MIGRATIONS)
+                .expect("Migrations should succeed"),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn time_travel() {
+        match script::render(MIGRATIONS, Some(4), None, Dialect::Sqlite) {
+            Err(MigrationError::TimeTravelError) => {}
+            result => panic!("Expected a time travel error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn placeholders() {
+        let migrations = [Migration::new(
+            1,
+            "create_accounts",
+            "CREATE TABLE ${prefix}accounts (id integer primary key);",
+        )];
+
+        let sql = script::Script::new(Dialect::Sqlite)
+            .with_placeholders(vec![("prefix", "tenant_")])
+            .render(migrations.clone(), None, None)
+            .expect("Rendering should succeed");
+        assert!(sql.contains("CREATE TABLE tenant_accounts"));
+        assert!(!sql.contains("${prefix}"));
+
+        let mut conn =
+            Connection::open_in_memory().expect("Failed to open an in-memory SQLite database");
+        conn.execute_batch(&sql).expect("The script should run");
+
+        conn.execute_batch("INSERT INTO tenant_accounts (id) VALUES (1);")
+            .expect("The rendered table should exist");
+
+        // Everything the script ran is tracked, so there is nothing left to migrate
+        assert_eq!(
+            MigrationRunner::new(&mut conn)
+                .with_placeholders(vec![("prefix", "tenant_")])
+                .migrate(migrations.clone())
+                .expect("Migrations should succeed"),
+            None
+        );
+
+        // Scripts written without placeholders keep the SQL as it is
+        let sql = script::render(migrations.clone(), None, None, Dialect::Sqlite)
+            .expect("Rendering should succeed");
+        assert!(sql.contains("CREATE TABLE ${prefix}accounts"));
+
+        match script::Script::new(Dialect::Sqlite)
+            .with_placeholders(vec![("schema", "main")])
+            .render(migrations, None, None)
+        {
+            Err(MigrationError::UnknownPlaceholder {
+                version: 1,
+                placeholder,
+                ..
+            }) => assert_eq!(placeholder, "prefix"),
+            result => panic!("Expected an unknown placeholder error, got {:?}", result),
+        }
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+mod squash {
+    use ::nomad::{squash, Driver, Migration, MigrationError, MigrationRunner};
+    use ::rusqlite::Connection;
+
+    const MIGRATIONS: [Migration; 3] = [
+        Migration {
+            version: 1,
+            name: ::std::borrow::Cow::Borrowed("create_users"),
+            sql: ::std::borrow::Cow::Borrowed("CREATE TABLE users (id integer primary key);"),
+            down: None,
+        },
+        Migration {
+            version: 2,
+            name: ::std::borrow::Cow::Borrowed("add_user_email"),
+            sql: ::std::borrow::Cow::Borrowed("ALTER TABLE users ADD COLUMN email text; CREATE UNIQUE INDEX users_email ON users(email);"),
+            down: None,
+        },
+        Migration {
+            version: 3,
+            name: ::std::borrow::Cow::Borrowed("create_posts"),
+            sql: ::std::borrow::Cow::Borrowed("CREATE TABLE posts (id integer primary key, title text);"),
+            down: None,
+        },
+    ];
+
+    fn open() -> Connection {
+        Connection::open_in_memory().expect("Failed to open an in-memory SQLite database")
+    }
+
+    fn schema(conn: &mut Connection) -> Vec<Vec<Option<String>>> {
+        conn.query_rows(
+            "SELECT sql FROM sqlite_master WHERE name IN ('users', 'users_email', 'posts') ORDER BY name",
+        )
+        .expect("Should be able to read the schema")
+    }
+
+    #[test]
+    fn baseline() {
+        let baseline = squash(&mut open(), MIGRATIONS, 2).expect("Squashing should succeed");
+        assert_eq!(baseline.file_name(), "2_baseline.sql");
+        assert!(baseline.sql.contains("CREATE UNIQUE INDEX users_email"));
+        assert!(!baseline.sql.contains("posts"));
+
+        let squashed = [baseline.migration(), MIGRATIONS[2].clone()];
+
+        // Fresh databases run the baseline
+        let mut fresh = open();
+        assert_eq!(
+            MigrationRunner::new(&mut fresh)
+                .migrate(squashed.clone())
+                .expect("Migrations should succeed"),
+            Some(3)
+        );
+
+        let mut migrated = open();
+        MigrationRunner::new(&mut migrated)
+            .migrate(MIGRATIONS)
+            .expect("Migrations should succeed");
+        assert_eq!(schema(&mut fresh), schema(&mut migrated));
+
+        // Existing databases skip it
+        let mut existing = open();
+        MigrationRunner::new(&mut existing)
+            .migrate([MIGRATIONS[0].clone(), MIGRATIONS[1].clone()])
+            .expect("Migrations should succeed");
+        assert_eq!(
+            MigrationRunner::new(&mut existing)
+                .migrate(squashed)
+                .expect("Migrations should succeed"),
+            Some(3)
+        );
+        assert_eq!(schema(&mut existing), schema(&mut migrated));
+    }
+
+    #[test]
+    fn covers_older_migrations() {
+        let baseline = squash(&mut open(), MIGRATIONS, 2).expect("Squashing should succeed");
+
+        // Migrations the baseline replaces are ignored, even if they haven't been deleted yet
+        let mut conn = open();
+        assert_eq!(
+            MigrationRunner::new(&mut conn)
+                .migrate([
+                    MIGRATIONS[0].clone(),
+                    baseline.migration(),
+                    MIGRATIONS[2].clone()
+                ])
+                .expect("Migrations should succeed"),
+            Some(3)
+        );
+        assert_eq!(
+            conn.applied_migrations(&Default::default(), ::nomad::DEFAULT_NAMESPACE)
+                .expect("Should be able to read the history")
+                .iter()
+                .map(|migration| migration.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["baseline", "create_posts"]
+        );
+    }
+
+    #[test]
+    fn behind_baseline() {
+        let baseline = squash(&mut open(), MIGRATIONS, 2).expect("Squashing should succeed");
+
+        let mut conn = open();
+        MigrationRunner::new(&mut conn)
+            .migrate([MIGRATIONS[0].clone()])
+            .expect("Migrations should succeed");
+
+        match MigrationRunner::new(&mut conn).migrate([baseline.migration(), MIGRATIONS[2].clone()])
+        {
+            Err(MigrationError::BehindBaseline {
+                latest: 1,
+                baseline: 2,
+            }) => {}
+            result => panic!("Expected a behind baseline error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn duplicate_version() {
+        let migrations = [
+            MIGRATIONS[0].clone(),
+            MIGRATIONS[1].clone(),
+            MIGRATIONS[1].clone(),
+        ];
+
+        match squash(&mut open(), migrations, 2) {
+            Err(MigrationError::DuplicateVersion { version: 2, .. }) => {}
+            result => panic!("Expected a DuplicateVersion error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn unknown_version() {
+        match squash(&mut open(), MIGRATIONS, 4) {
+            Err(MigrationError::UnknownVersion { version: 4 }) => {}
+            result => panic!("Expected an unknown version error, got {:?}", result),
+        }
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+mod testing {
+    use ::nomad::testing::{assert_schema_snapshot, schema_snapshot, MigrationHarness};
+    use ::nomad::Migration;
+    use ::rusqlite::Connection;
+
+    const MIGRATIONS: [Migration; 3] = [
+        Migration {
+            version: 1,
+            name: ::std::borrow::Cow::Borrowed("create_users"),
+            sql: ::std::borrow::Cow::Borrowed("CREATE TABLE users (id integer primary key);"),
+            down: None,
+        },
+        Migration {
+            version: 2,
+            name: ::std::borrow::Cow::Borrowed("add_user_email"),
+            sql: ::std::borrow::Cow::Borrowed("ALTER TABLE users ADD COLUMN email text;\nCREATE UNIQUE INDEX users_email ON users(email);"),
+            down: None,
+        },
+        Migration {
+            version: 3,
+            name: ::std::borrow::Cow::Borrowed("create_posts"),
+            sql: ::std::borrow::Cow::Borrowed("CREATE TABLE posts (\n    id    integer primary key,\n    title text default 'a  b'\n);"),
+            down: None,
+        },
+    ];
+
+    fn open() -> Connection {
+        Connection::open_in_memory().expect("Failed to open an in-memory SQLite database")
+    }
+
+    #[test]
+    fn normalized() {
+        let snapshot = schema_snapshot(&mut open(), MIGRATIONS).expect("Migrations should succeed");
+
+        assert_eq!(
+            snapshot.lines().collect::<Vec<_>>(),
+            vec![
+                "CREATE TABLE posts (id integer primary key, title text default 'a  b');",
+                "",
+                "CREATE TABLE users (id integer primary key, email text);",
+                "",
+                "CREATE UNIQUE INDEX users_email ON users(email);",
+            ]
+        );
+    }
+
+    #[test]
+    fn matches_snapshot() {
+        assert_schema_snapshot(
+            &mut open(),
+            MIGRATIONS,
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/schema.sql"),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "+ CREATE TABLE comments (id integer primary key);")]
+    fn drift() {
+        let mut migrations = MIGRATIONS.to_vec();
+        migrations.push(Migration {
+            version: 4,
+            name: ::std::borrow::Cow::Borrowed("create_comments"),
+            sql: ::std::borrow::Cow::Borrowed("CREATE TABLE comments (id integer primary key);"),
+            down: None,
+        });
+
+        // A copy, so running with NOMAD_UPDATE_SNAPSHOT never writes the drifted schema to the real snapshot
+        struct Copy(::std::path::PathBuf);
+
+        impl Drop for Copy {
+            fn drop(&mut self) {
+                let _ = ::std::fs::remove_file(&self.0);
+            }
+        }
+
+        let snapshot =
+            Copy(::std::env::temp_dir().join(format!("nomad-drift-{}.sql", ::std::process::id())));
+        ::std::fs::copy(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/schema.sql"),
+            &snapshot.0,
+        )
+        .expect("Failed to copy the snapshot");
+
+        assert_schema_snapshot(&mut open(), migrations, &snapshot.0);
+    }
+
+    #[test]
+    fn harness() {
+        let migrations = [
+            MIGRATIONS[0].clone().with_down("DROP TABLE users;"),
+            // SQLite can't drop columns, so only the index is reverted
+            MIGRATIONS[1].clone().with_down("DROP INDEX users_email;"),
+            MIGRATIONS[2].clone().with_down("DROP TABLE posts;"),
+        ];
+
+        MigrationHarness::new(open).assert(MIGRATIONS);
+
+        let failures = MigrationHarness::new(open)
+            .with_down_migrations(true)
+            .run(migrations);
+
+        assert_eq!(
+            failures
+                .iter()
+                .map(|failure| (failure.version, failure.reason.lines().next().unwrap_or("")))
+                .collect::<Vec<_>>(),
+            vec![(2, "The down migration left a different schema")]
+        );
+        assert!(failures[0]
+            .reason
+            .contains("+ CREATE TABLE users (id integer primary key, email text);"));
+    }
+
+    #[test]
+    fn harness_stops_at_failure() {
+        let mut migrations = MIGRATIONS.to_vec();
+        migrations[1].sql = "CREATE INDEX users_email ON users(email);".into();
+
+        let failures = MigrationHarness::new(open).run(migrations);
+
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].version, 2);
+        assert!(failures[0].reason.starts_with("Failed to apply"));
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+mod verify {
+    use ::nomad::{Migration, MigrationRunner, SchemaDifference};
+    use ::rusqlite::Connection;
+
+    const MIGRATIONS: [Migration; 3] = [
+        Migration {
+            version: 1,
+            name: ::std::borrow::Cow::Borrowed("create_users"),
+            sql: ::std::borrow::Cow::Borrowed("CREATE TABLE users (id integer primary key);"),
+            down: None,
+        },
+        Migration {
+            version: 2,
+            name: ::std::borrow::Cow::Borrowed("add_user_email"),
+            sql: ::std::borrow::Cow::Borrowed("ALTER TABLE users ADD COLUMN email text; CREATE UNIQUE INDEX users_email ON users(email);"),
+            down: None,
+        },
+        Migration {
+            version: 3,
+            name: ::std::borrow::Cow::Borrowed("create_posts"),
+            sql: ::std::borrow::Cow::Borrowed("CREATE TABLE posts (id integer primary key, title text);"),
+            down: None,
+        },
+    ];
+
+    fn open() -> Connection {
+        Connection::open_in_memory().expect("Failed to open an in-memory SQLite database")
+    }
+
+    fn verify(conn: &mut Connection) -> Vec<SchemaDifference> {
+        MigrationRunner::new(conn)
+            .verify_schema(MIGRATIONS, &mut open())
+            .expect("Verification should succeed")
+    }
+
+    #[test]
+    fn matching() {
+        let mut conn = open();
+        MigrationRunner::new(&mut conn)
+            .migrate([MIGRATIONS[0].clone(), MIGRATIONS[1].clone()])
+            .expect("Migrations should succeed");
+
+        // Only applied migrations are expected
+        assert_eq!(verify(&mut conn), vec![]);
+    }
+
+    #[test]
+    fn hotfixes() {
+        let mut conn = open();
+        MigrationRunner::new(&mut conn)
+            .migrate(MIGRATIONS)
+            .expect("Migrations should succeed");
+
+        conn.execute_batch(
+            "DROP INDEX users_email; CREATE INDEX posts_title ON posts(title); ALTER TABLE posts ADD COLUMN body text;",
+        )
+        .expect("Failed to apply hotfixes");
+
+        assert_eq!(
+            verify(&mut conn),
+            vec![
+                SchemaDifference::ExtraColumn {
+                    table: "posts".to_owned(),
+                    column: "body".to_owned(),
+                },
+                SchemaDifference::MissingObject {
+                    kind: "index".to_owned(),
+                    name: "users_email".to_owned(),
+                },
+                SchemaDifference::ExtraObject {
+                    kind: "index".to_owned(),
+                    name: "posts_title".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn changed_column() {
+        let mut conn = open();
+        MigrationRunner::new(&mut conn)
+            .migrate([MIGRATIONS[0].clone()])
+            .expect("Migrations should succeed");
+
+        conn.execute_batch("DROP TABLE users; CREATE TABLE users (id text primary key);")
+            .expect("Failed to apply hotfixes");
+
+        assert_eq!(
+            verify(&mut conn),
+            vec![SchemaDifference::ChangedColumn {
+                table: "users".to_owned(),
+                column: "id".to_owned(),
+                expected: "integer primary key".to_owned(),
+                actual: "text primary key".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn other_namespaces() {
+        let billing = [
+            Migration::new(
+                1,
+                "create_invoices",
+                "CREATE TABLE invoices (id integer primary key, user_id integer);",
+            ),
+            Migration::new(
+                2,
+                "index_invoices",
+                "CREATE INDEX invoices_user ON invoices(user_id);",
+            ),
+        ];
+
+        let mut conn = open();
+        let mut registry = ::nomad::MigrationRegistry::new();
+        registry
+            .register("core", MIGRATIONS)
+            .and_then(|registry| registry.register("billing", billing.clone()))
+            .expect("Namespaces should be unique");
+        registry
+            .migrate_all(&mut conn)
+            .expect("Migrations should succeed");
+
+        let verify_namespace = |conn: &mut Connection, namespace, migrations: &[Migration]| {
+            MigrationRunner::with_namespace(conn, namespace)
+                .verify_schema(migrations.to_vec(), &mut open())
+                .expect("Verification should succeed")
+        };
+
+        // Each namespace's objects are expected by that namespace alone
+        assert_eq!(verify_namespace(&mut conn, "core", &MIGRATIONS), vec![]);
+        assert_eq!(verify_namespace(&mut conn, "billing", &billing), vec![]);
+
+        // Objects on a namespace's tables are still its own
+        conn.execute_batch("CREATE INDEX invoices_id ON invoices(id, user_id);")
+            .expect("Failed to apply hotfixes");
+        assert_eq!(verify_namespace(&mut conn, "core", &MIGRATIONS), vec![]);
+        assert_eq!(
+            verify_namespace(&mut conn, "billing", &billing),
+            vec![SchemaDifference::ExtraObject {
+                kind: "index".to_owned(),
+                name: "invoices_id".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn dropped_tables() {
+        let migrations = [
+            MIGRATIONS[0].clone(),
+            Migration::new(2, "create_drafts", "CREATE TABLE drafts (id integer);"),
+            Migration::new(3, "drop_drafts", "DROP TABLE drafts;"),
+        ];
+
+        let mut conn = open();
+        MigrationRunner::new(&mut conn)
+            .migrate(migrations.clone())
+            .expect("Migrations should succeed");
+
+        // A table a migration dropped is still this namespace's, so coming back is reported
+        conn.execute_batch("CREATE TABLE drafts (id integer);")
+            .expect("Failed to apply hotfixes");
+        assert_eq!(
+            MigrationRunner::new(&mut conn)
+                .verify_schema(migrations, &mut open())
+                .expect("Verification should succeed"),
+            vec![SchemaDifference::ExtraObject {
+                kind: "table".to_owned(),
+                name: "drafts".to_owned(),
+            }]
+        );
+    }
+}
+
+mod scaffold {
+    use ::nomad::scaffold::{NewMigration, VersionScheme};
+    use ::nomad::Timestamp;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("nomad-scaffold-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn sequential() {
+        let dir = scratch_dir("sequential");
+
+        let first = NewMigration::new("create_users")
+            .create(&dir)
+            .expect("Failed to create a migration");
+        assert_eq!(first.version, 1);
+        assert_eq!(first.path, dir.join("1_create_users.sql"));
+        assert_eq!(first.down_path, None);
+
+        let second = NewMigration::new("create_posts")
+            .with_down(true)
+            .with_header("-- {version} {name} {direction}\n")
+            .create(&dir)
+            .expect("Failed to create a migration");
+        assert_eq!(second.version, 2);
+        assert_eq!(
+            fs::read_to_string(&second.path).unwrap(),
+            "-- 2 create_posts up\n"
+        );
+        assert_eq!(second.down_path, Some(dir.join("2_create_posts.down.sql")));
+        assert_eq!(
+            fs::read_to_string(dir.join("2_create_posts.down.sql")).unwrap(),
+            "-- 2 create_posts down\n"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn timestamp() {
+        let dir = scratch_dir("timestamp");
+        let before = Timestamp::now().version();
+
+        let created = NewMigration::new("create_users")
+            .with_scheme(VersionScheme::Timestamp)
+            .create(&dir)
+            .expect("Failed to create a migration");
+        assert!(created.version >= before);
+        assert!(Timestamp::from_version(created.version).is_some());
+
+        // Migrations from a clock running ahead still come first
+        fs::write(dir.join("99990101000000_future.sql"), "").unwrap();
+        let created = NewMigration::new("create_posts")
+            .with_scheme(VersionScheme::Timestamp)
+            .create(&dir)
+            .expect("Failed to create a migration");
+        assert_eq!(created.version, 99990101000001);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn existing_layouts() {
+        let dir = scratch_dir("layouts");
+        fs::create_dir_all(dir.join("2021-03-04-050607_diesel")).unwrap();
+        fs::write(dir.join("2021-03-04-050607_diesel/up.sql"), "").unwrap();
+        fs::write(dir.join("7_plain.sql"), "").unwrap();
+
+        let created = NewMigration::new("next")
+            .create(&dir)
+            .expect("Failed to create a migration");
+        assert_eq!(created.version, 20210304050608);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_names() {
+        let dir = scratch_dir("invalid-names");
+
+        for name in &[
+            "",
+            "foo.down",
+            "foo.up",
+            "../escape",
+            "nested/name",
+            "win\\name",
+        ] {
+            match NewMigration::new(name).create(&dir) {
+                Err(::nomad::FileError::InvalidName(_)) => {}
+                result => panic!("Expected {:?} to be invalid, got {:?}", name, result),
+            }
+        }
+
+        // Nothing was written, so the directory still reads back cleanly
+        assert!(!dir.exists());
+        assert!(!dir.parent().unwrap().join("escape.sql").exists());
+
+        fs::create_dir_all(&dir).unwrap();
+        NewMigration::new("foo.downgrade")
+            .create(&dir)
+            .expect("Failed to create a migration");
+        assert_eq!(
+            ::nomad::load_dir(&dir)
+                .expect("Migrations should load")
+                .as_ref()
+                .iter()
+                .map(|migration| migration.name.to_string())
+                .collect::<Vec<_>>(),
+            ["foo.downgrade"]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+mod cli {
+    use std::fs;
+    use std::path::PathBuf;
+    use std::process::Command;
+
+    // A scratch directory holding a database and its migrations, removed when dropped
+    struct Workspace(PathBuf);
+
+    impl Workspace {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("nomad-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(dir.join("migrations")).expect("Failed to create the workspace");
+
+            let workspace = Workspace(dir);
+            workspace.write(
+                "1_create_users.sql",
+                "CREATE TABLE users (id integer primary key);",
+            );
+            workspace.write("1_create_users.down.sql", "DROP TABLE users;");
+            workspace.write(
+                "2_create_posts.sql",
+                "CREATE TABLE posts (id integer primary key);",
+            );
+            workspace.write("2_create_posts.down.sql", "DROP TABLE posts;");
+            workspace
+        }
+
+        fn write(&self, file_name: &str, sql: &str) {
+            fs::write(self.0.join("migrations").join(file_name), sql)
+                .expect("Failed to write a migration");
+        }
+
+        fn nomad(&self, args: &[&str]) -> (bool, String) {
+            let output = Command::new(env!("CARGO_BIN_EXE_nomad"))
+                .args(args)
+                .arg("--database")
+                .arg(self.0.join("test.db"))
+                .arg("--migrations")
+                .arg(self.0.join("migrations"))
+                .env_remove("DATABASE_URL")
+                .output()
+                .expect("Failed to run nomad");
+
+            (
+                output.status.success(),
+                String::from_utf8_lossy(&output.stdout).into_owned()
+                    + &String::from_utf8_lossy(&output.stderr),
+            )
+        }
+    }
+
+    impl Drop for Workspace {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn migrate() {
+        let workspace = Workspace::new("migrate");
+
+        let (success, output) = workspace.nomad(&["migrate", "--to", "1"]);
+        assert!(success, "{}", output);
+        assert_eq!(output, "Applied 1 create_users\n");
+
+        let (success, output) = workspace.nomad(&["status"]);
+        assert!(success, "{}", output);
+        assert!(output.contains("applied") && output.contains("1 create_users"));
+        assert!(output.contains("pending") && output.contains("2 create_posts"));
+
+        let (success, output) = workspace.nomad(&["plan"]);
+        assert!(success, "{}", output);
+        assert!(output.contains("CREATE TABLE posts"));
+        assert!(!output.contains("CREATE TABLE users"));
+
+        let (success, output) = workspace.nomad(&["migrate"]);
+        assert!(success, "{}", output);
+        assert_eq!(output, "Applied 2 create_posts\n");
+
+        let (success, output) = workspace.nomad(&["rollback"]);
+        assert!(success, "{}", output);
+        assert_eq!(output, "Reverted 2 create_posts\n");
+
+        let (success, output) = workspace.nomad(&["verify"]);
+        assert!(success, "{}", output);
+    }
 
-            let mut driver = $driver;
+    #[test]
+    fn tracking_table() {
+        let workspace = Workspace::new("tracking-table");
 
-            if let Ok(_) = ::nomad::MigrationRunner::new(&mut driver).migrate(migrations) {
-                panic!("Migrations should fail");
-            }
+        let (success, output) =
+            workspace.nomad(&["migrate", "--tracking-table", "_schema_history"]);
+        assert!(success, "{}", output);
 
-            assert_eq!(
-                driver
-                    .latest_version(::nomad::DEFAULT_NAMESPACE)
-                    .expect("Should be able to get a version"),
-                None
-            );
-        }
-    };
-}
+        let (success, output) = workspace.nomad(&["status", "--tracking-table", "_schema_history"]);
+        assert!(success, "{}", output);
+        assert!(!output.contains("pending"), "{}", output);
 
-mod dummy {
-    use std::collections::HashMap;
-    use std::fmt::Display;
+        let (success, output) = workspace.nomad(&["plan", "--tracking-table", "_schema_history"]);
+        assert!(success, "{}", output);
+        assert!(!output.contains("CREATE TABLE"), "{}", output);
 
-    #[derive(Debug)]
-    struct DummyError();
+        let (success, output) = workspace.nomad(&["verify", "--tracking-table", "_schema_history"]);
+        assert!(success, "{}", output);
 
-    impl Display for DummyError {
-        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-            write!(f, "{:?}", self)
-        }
+        let (success, output) =
+            workspace.nomad(&["rollback", "--tracking-table", "_schema_history"]);
+        assert!(success, "{}", output);
+        assert_eq!(output, "Reverted 2 create_posts\n");
+
+        // Other tracking tables know nothing of these migrations
+        let (success, output) = workspace.nomad(&["status"]);
+        assert!(success, "{}", output);
+        assert!(!output.contains("applied"), "{}", output);
     }
 
-    impl std::error::Error for DummyError {}
+    #[test]
+    fn placeholders() {
+        let workspace = Workspace::new("placeholders");
+        workspace.write(
+            "3_create_tags.sql",
+            "CREATE TABLE ${prefix}tags (id integer primary key);",
+        );
+        workspace.write("3_create_tags.down.sql", "DROP TABLE ${prefix}tags;");
 
-    #[derive(Default)]
-    struct DummyDriver {
-        latest_versions: HashMap<String, u64>,
+        let (success, output) = workspace.nomad(&["migrate", "-p", "schema=main"]);
+        assert!(!success);
+        assert!(output.contains("${prefix}"), "{}", output);
+
+        let (success, output) = workspace.nomad(&["plan", "-p", "prefix=app_"]);
+        assert!(success, "{}", output);
+        assert!(output.contains("CREATE TABLE app_tags"), "{}", output);
+
+        let (success, output) = workspace.nomad(&["migrate", "-p", "prefix=app_"]);
+        assert!(success, "{}", output);
+
+        let (success, output) = workspace.nomad(&["verify", "--placeholder", "prefix=app_"]);
+        assert!(success, "{}", output);
+
+        // A different value changes the SQL that was already applied, which only fails when checked
+        let (success, output) = workspace.nomad(&["migrate", "-p", "prefix=other_"]);
+        assert!(success, "{}", output);
+
+        let (success, output) =
+            workspace.nomad(&["migrate", "-p", "prefix=other_", "--verify-checksums"]);
+        assert!(!success);
+        assert!(
+            output.contains("has changed since it was applied"),
+            "{}",
+            output
+        );
+
+        let (success, output) = workspace.nomad(&["rollback", "-p", "prefix=app_"]);
+        assert!(success, "{}", output);
+        assert_eq!(output, "Reverted 3 create_tags\n");
+
+        let (success, output) = workspace.nomad(&["plan", "-p", "prefix"]);
+        assert!(!success);
+        assert!(output.contains("not a name=value pair"), "{}", output);
     }
 
-    struct DummyTransaction<'a> {
-        driver: &'a mut DummyDriver,
-        changes: HashMap<String, u64>,
+    #[test]
+    fn verify() {
+        let workspace = Workspace::new("verify");
+
+        let (success, output) = workspace.nomad(&["migrate"]);
+        assert!(success, "{}", output);
+
+        ::rusqlite::Connection::open(workspace.0.join("test.db"))
+            .expect("Failed to open the database")
+            .execute_batch("CREATE INDEX posts_id ON posts(id);")
+            .expect("Failed to apply a hotfix");
+
+        let (success, output) = workspace.nomad(&["verify"]);
+        assert!(!success);
+        assert!(output.contains("posts_id"), "{}", output);
     }
 
-    impl ::nomad::Transaction<'_, DummyDriver> for DummyTransaction<'_> {
-        fn commit(self) -> Result<(), DummyError> {
-            for (k, v) in self.changes {
-                self.driver.latest_versions.insert(k, v);
+    #[test]
+    fn new_and_baseline() {
+        let workspace = Workspace::new("baseline");
+
+        let (success, output) = workspace.nomad(&["new", "create_comments"]);
+        assert!(success, "{}", output);
+        assert!(workspace
+            .0
+            .join("migrations/3_create_comments.sql")
+            .exists());
+
+        let (success, output) = workspace.nomad(&["baseline", "2"]);
+        assert!(success, "{}", output);
+        assert!(workspace.0.join("migrations/2_baseline.sql").exists());
+        assert!(!workspace.0.join("migrations/1_create_users.sql").exists());
+
+        let (success, output) = workspace.nomad(&["migrate"]);
+        assert!(success, "{}", output);
+        assert_eq!(output, "Applied 2 baseline\nApplied 3 create_comments\n");
+
+        // Squashing through the baseline again replaces it rather than removing it
+        let (success, output) = workspace.nomad(&["baseline", "3"]);
+        assert!(success, "{}", output);
+        assert!(workspace.0.join("migrations/3_baseline.sql").exists());
+        assert!(!workspace.0.join("migrations/2_baseline.sql").exists());
+
+        let (success, output) = workspace.nomad(&["baseline", "3"]);
+        assert!(success, "{}", output);
+        assert!(workspace.0.join("migrations/3_baseline.sql").exists());
+    }
+
+    #[test]
+    fn baseline_write_failure() {
+        let workspace = Workspace::new("baseline-failure");
+
+        // A directory in the way of the baseline file makes writing it fail
+        fs::create_dir(workspace.0.join("migrations/2_baseline.sql"))
+            .expect("Failed to create a directory");
+
+        let (success, output) = workspace.nomad(&["baseline", "2"]);
+        assert!(!success, "{}", output);
+        assert!(workspace.0.join("migrations/1_create_users.sql").exists());
+        assert!(workspace.0.join("migrations/2_create_posts.sql").exists());
+    }
+
+    #[test]
+    fn usage() {
+        let workspace = Workspace::new("usage");
+
+        let (success, output) = workspace.nomad(&["fly"]);
+        assert!(!success);
+        assert!(output.contains("Unknown command fly"));
+    }
+}
+
+mod files {
+    use ::nomad_files::{read_dir, Convention, FileError, ReadOptions};
+    use std::fs;
+
+    #[test]
+    fn reports_every_problem() {
+        let dir = std::env::temp_dir().join(format!("nomad-files-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(dir.join("1_valid.sql"), "").unwrap();
+        fs::write(dir.join("readme.md"), "").unwrap();
+        fs::write(dir.join("2_orphan.down.sql"), "").unwrap();
+
+        let err = ReadOptions::new()
+            .with_strict(true)
+            .read_dir(&dir)
+            .expect_err("Invalid migrations were read");
+        let mut errors = err
+            .into_errors()
+            .into_iter()
+            .map(|err| match err {
+                FileError::InvalidName(path) => format!("invalid {}", path.display()),
+                FileError::UnmatchedDown { version, .. } => format!("unmatched {}", version),
+                FileError::UnexpectedNamespace(path) => format!("namespace {}", path.display()),
+                err => panic!("Unexpected error {}", err),
+            })
+            .collect::<Vec<_>>();
+        errors.sort();
+
+        assert_eq!(
+            errors,
+            [
+                format!("invalid {}", dir.join("readme.md").display()),
+                format!("namespace {}", dir.join("nested").display()),
+                "unmatched 2".to_owned(),
+            ]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn skips_other_files() {
+        let dir = std::env::temp_dir().join(format!("nomad-skips-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("1_valid.sql"), "").unwrap();
+        fs::write(dir.join("2_draft.sql"), "").unwrap();
+        fs::write(dir.join("readme.md"), "").unwrap();
+        fs::write(dir.join(".gitkeep"), "").unwrap();
+
+        let names = |options: ReadOptions| {
+            options
+                .read_dir(&dir)
+                .expect("Failed to read migrations")
+                .into_iter()
+                .map(|migration| migration.name)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names(ReadOptions::new()), ["valid", "draft"]);
+        assert_eq!(
+            names(ReadOptions::new().with_ignore("*_dr?ft.sql")),
+            ["valid"]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn flyway_dotted_versions() {
+        let dir = std::env::temp_dir().join(format!("nomad-dotted-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("V1__create_users.sql"), "").unwrap();
+        fs::write(dir.join("V1.1__add_email.sql"), "").unwrap();
+        fs::write(dir.join("V1_2__add_name.sql"), "").unwrap();
+
+        match ReadOptions::new()
+            .with_convention(Convention::Flyway)
+            .read_dir(&dir)
+            .map_err(FileError::into_errors)
+        {
+            Err(errors) => {
+                let mut versions = errors
+                    .iter()
+                    .map(|err| match err {
+                        FileError::UnsupportedVersion { version, .. } => version.as_str(),
+                        err => panic!("Expected an unsupported version error, got {:?}", err),
+                    })
+                    .collect::<Vec<_>>();
+                versions.sort_unstable();
+
+                assert_eq!(versions, ["1.1", "1_2"]);
             }
+            result => panic!("Expected unsupported version errors, got {:?}", result),
+        }
 
-            Ok(())
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn optional_version() {
+        let dir = std::env::temp_dir().join(format!("nomad-optional-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("1_valid.sql"), "").unwrap();
+        fs::write(dir.join("unversioned.sql"), "").unwrap();
+
+        let options = ReadOptions::new()
+            .with_pattern(r"^(?:(?P<version>\d+)_)?(?P<name>.*)\.sql$")
+            .expect("The pattern should be valid");
+
+        match options.read_dir(&dir) {
+            Err(FileError::InvalidName(path)) => assert_eq!(path, dir.join("unversioned.sql")),
+            result => panic!("Expected an invalid name error, got {:?}", result),
         }
 
-        fn execute_sql(&mut self, sql: &str) -> Result<(), DummyError> {
-            if sql == "evil" {
-                Err(DummyError())
-            } else {
-                Ok(())
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn flyway_repeatable() {
+        let dir = std::env::temp_dir().join(format!("nomad-repeatable-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("V1__create_users.sql"), "").unwrap();
+        fs::write(dir.join("R__users_view.sql"), "").unwrap();
+
+        let options = ReadOptions::new().with_convention(Convention::Flyway);
+        assert_eq!(
+            options
+                .read_dir(&dir)
+                .expect("Failed to read migrations")
+                .into_iter()
+                .map(|migration| migration.name)
+                .collect::<Vec<_>>(),
+            ["create_users"]
+        );
+
+        match options.with_strict(true).read_dir(&dir) {
+            Err(FileError::RepeatableMigration(path)) => {
+                assert_eq!(path, dir.join("R__users_view.sql"))
             }
+            result => panic!("Expected a repeatable migration error, got {:?}", result),
         }
 
-        fn push_latest_version(&mut self, namespace: &str, version: u64) -> Result<(), DummyError> {
-            self.changes.insert(namespace.to_owned(), version);
+        fs::remove_dir_all(&dir).unwrap();
+    }
 
-            Ok(())
+    #[test]
+    fn invalid_pattern() {
+        match ReadOptions::new().with_pattern(r"^(\d+)_(.*)\.sql$") {
+            Err(FileError::InvalidPattern { .. }) => {}
+            result => panic!(
+                "Expected an invalid pattern error, got {:?}",
+                result.map(|_| ())
+            ),
         }
     }
 
-    impl<'a> ::nomad::Driver<'a> for DummyDriver {
-        type Transaction = DummyTransaction<'a>;
-        type Error = DummyError;
+    #[test]
+    fn duplicate_versions() {
+        let dir = std::env::temp_dir().join(format!("nomad-duplicates-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("1_first.sql"), "").unwrap();
+        fs::write(dir.join("2_a.sql"), "").unwrap();
+        fs::write(dir.join("2_b.sql"), "").unwrap();
 
-        fn begin(&'a mut self) -> Result<Self::Transaction, Self::Error> {
-            Ok(DummyTransaction {
-                driver: self,
-                changes: Default::default(),
-            })
+        match read_dir(&dir) {
+            Err(FileError::DuplicateVersion {
+                version,
+                first,
+                second,
+            }) => {
+                assert_eq!(version, 2);
+                assert_eq!(first, dir.join("2_a.sql"));
+                assert_eq!(second, dir.join("2_b.sql"));
+            }
+            result => panic!("Expected a duplicate version error, got {:?}", result),
         }
 
-        fn latest_version(&mut self, namespace: &str) -> Result<Option<u64>, Self::Error> {
-            Ok(self.latest_versions.get(&namespace.to_owned()).map(|n| *n))
-        }
+        fs::remove_dir_all(&dir).unwrap();
     }
+}
 
-    driver_tests!(DummyDriver::default());
+mod load {
+    use ::nomad::{load_dir, load_dir_with, Convention, ReadOptions};
+
+    #[test]
+    fn matches_embedded() {
+        let loaded = load_dir("./tests/diesel").expect("Failed to load migrations");
+        let embedded = ::nomad::nomad_migrations!("./tests/diesel");
+
+        assert_eq!(
+            loaded
+                .iter()
+                .map(|m| (m.version, m.name.clone(), m.sql.clone(), m.down.clone()))
+                .collect::<Vec<_>>(),
+            embedded
+                .iter()
+                .map(|m| (m.version, m.name.clone(), m.sql.clone(), m.down.clone()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn options() {
+        let loaded = load_dir_with(
+            "./tests/flyway",
+            &ReadOptions::new().with_convention(Convention::Flyway),
+        )
+        .expect("Failed to load migrations");
+
+        assert_eq!(
+            loaded
+                .iter()
+                .map(|m| (m.version, &*m.name, m.down.is_some()))
+                .collect::<Vec<_>>(),
+            [(1, "create_users", true), (2, "create_posts", false)]
+        );
+    }
+
+    #[cfg(feature = "rusqlite")]
+    #[test]
+    fn owned_sql() {
+        use ::nomad::{Migration, MigrationRunner};
+
+        // Names and SQL built at runtime are owned by their migration, rather than borrowed from elsewhere
+        let migrations = ["users", "posts"]
+            .iter()
+            .enumerate()
+            .map(|(index, table)| {
+                Migration::new(
+                    index as u64 + 1,
+                    format!("create_{}", table),
+                    format!("CREATE TABLE {} (id integer primary key);", table),
+                )
+                .with_down(format!("DROP TABLE {};", table))
+            })
+            .collect::<Vec<_>>();
+
+        let mut conn = ::rusqlite::Connection::open_in_memory().expect("Failed to open database");
+        assert_eq!(
+            MigrationRunner::new(&mut conn)
+                .migrate(migrations.clone())
+                .expect("Failed to migrate"),
+            Some(2)
+        );
+        assert_eq!(
+            MigrationRunner::new(&mut conn)
+                .rollback(migrations, None)
+                .expect("Failed to roll back")
+                .len(),
+            2
+        );
+    }
+
+    #[cfg(feature = "rusqlite")]
+    #[test]
+    fn migrate() {
+        let loaded = load_dir("./tests/diesel").expect("Failed to load migrations");
+        let mut conn = ::rusqlite::Connection::open_in_memory().expect("Failed to open database");
+
+        assert_eq!(
+            ::nomad::MigrationRunner::new(&mut conn)
+                .migrate(loaded)
+                .expect("Failed to migrate"),
+            Some(20201020090000)
+        );
+    }
 }
 
-#[cfg(feature = "sqlx")]
-mod sqlx {
-    #[cfg(feature = "sqlx-sqlite")]
-    mod sqlite {
-        use ::sqlx::sqlite::SqliteConnection;
-        use ::sqlx::Connection;
-        use futures::executor::block_on;
+mod build {
+    use ::nomad::build::{embed_migrations, EmbedMigrations};
+    use ::nomad::{Convention, FileError, ReadOptions};
+    use std::fs;
+    use std::path::Path;
 
-        driver_tests!(block_on(SqliteConnection::connect("sqlite::memory:"))
-            .expect("Falied to open an in-memory SQLite database"));
+    fn scratch_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("nomad-build-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
     }
+
+    fn include(path: &Path) -> String {
+        format!("::std::include_str!({:?})", path.to_str().unwrap())
+    }
+
+    #[test]
+    fn embed() {
+        let out_dir = scratch_dir("embed");
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/flyway");
+
+        let path = EmbedMigrations::new("tests/flyway")
+            .with_options(ReadOptions::new().with_convention(Convention::Flyway))
+            .with_out_dir(&out_dir)
+            .with_file_name("flyway.rs")
+            .write()
+            .expect("Failed to embed migrations");
+        assert_eq!(path, out_dir.join("flyway.rs"));
+
+        let module = fs::read_to_string(&path).unwrap();
+        assert!(module.contains("pub static MIGRATIONS: [::nomad::Migration<'static>; 2]"));
+        assert!(module.contains(&include(&dir.join("V1__create_users.sql"))));
+        assert!(module.contains(&include(&dir.join("U1__create_users.sql"))));
+        assert!(module.contains(&include(&dir.join("V2__create_posts.sql"))));
+        assert!(module.contains("pub fn migrations()"));
+
+        fs::remove_dir_all(&out_dir).unwrap();
+    }
+
+    // Build a crate that embeds migrations from its build script and includes the module, as the docs describe
+    #[test]
+    fn embed_compiles() {
+        let crate_dir = scratch_dir("crate");
+        let nomad = env!("CARGO_MANIFEST_DIR");
+        let dir = Path::new(nomad).join("tests/flyway");
+
+        fs::write(
+            crate_dir.join("Cargo.toml"),
+            format!(
+                "[package]\nname = \"embed\"\nversion = \"0.0.0\"\nedition = \"2018\"\n\n[workspace]\n\n\
+                 [dependencies]\nnomad = {{ path = {0:?} }}\n\n[build-dependencies]\nnomad = {{ path = {0:?} }}\n",
+                nomad
+            ),
+        )
+        .unwrap();
+        // The same versions the tests were built with, which also lets cargo run offline
+        fs::copy(
+            Path::new(nomad).join("Cargo.lock"),
+            crate_dir.join("Cargo.lock"),
+        )
+        .unwrap();
+        fs::write(
+            crate_dir.join("build.rs"),
+            format!(
+                "fn main() {{\n\
+                 nomad::build::EmbedMigrations::new({:?})\n\
+                 .with_options(nomad::ReadOptions::new().with_convention(nomad::Convention::Flyway))\n\
+                 .write()\n\
+                 .unwrap();\n\
+                 }}\n",
+                dir.to_str().unwrap()
+            ),
+        )
+        .unwrap();
+        fs::create_dir_all(crate_dir.join("src")).unwrap();
+        fs::write(
+            crate_dir.join("src/main.rs"),
+            r#"mod migrations {
+    include!(concat!(env!("OUT_DIR"), "/nomad_migrations.rs"));
 }
 
-#[cfg(feature = "rusqlite")]
-mod rusqlite {
-    use ::rusqlite::Connection;
+fn main() {
+    let all: &[nomad::Migration<'static>; 2] = &migrations::MIGRATIONS;
+    assert_eq!(all.len(), migrations::migrations().as_ref().len());
 
-    driver_tests!(
-        Connection::open_in_memory().expect("Failed to open an in-memory SQLite database")
-    );
+    for migration in migrations::migrations().as_ref() {
+        println!("{} {} {:?} {:?}", migration.version, migration.name, migration.sql, migration.down);
+    }
+}
+"#,
+        )
+        .unwrap();
+
+        // Shared between runs, so only the first has to build nomad's dependencies
+        let output = std::process::Command::new(option_env!("CARGO").unwrap_or("cargo"))
+            .args(["run", "--quiet", "--offline"])
+            .current_dir(&crate_dir)
+            .env(
+                "CARGO_TARGET_DIR",
+                std::env::temp_dir().join("nomad-build-target"),
+            )
+            .output()
+            .expect("Failed to run cargo");
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        let read = |name: &str| fs::read_to_string(dir.join(name)).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            format!(
+                "1 create_users {:?} Some({:?})\n2 create_posts {:?} None\n",
+                read("V1__create_users.sql"),
+                read("U1__create_users.sql"),
+                read("V2__create_posts.sql"),
+            )
+        );
+
+        fs::remove_dir_all(&crate_dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_migrations() {
+        let out_dir = scratch_dir("invalid");
+        fs::write(out_dir.join("1_a.sql"), "").unwrap();
+        fs::write(out_dir.join("1_b.sql"), "").unwrap();
+
+        assert!(EmbedMigrations::new(&out_dir)
+            .with_out_dir(&out_dir)
+            .write()
+            .is_err());
+        assert!(!out_dir.join("nomad_migrations.rs").exists());
+
+        fs::remove_dir_all(&out_dir).unwrap();
+    }
+
+    #[test]
+    fn outside_build_script() {
+        match embed_migrations("tests/migrations") {
+            Err(err @ FileError::Io { .. }) => {
+                assert!(err.to_string().contains("OUT_DIR is not set"))
+            }
+            result => panic!("Expected an Io error, got {:?}", result),
+        }
+    }
+}
+
+mod embedded {
+    #[test]
+    fn names() {
+        let migrations = ::nomad::nomad_migrations!("./tests/migrations");
+
+        assert_eq!(
+            migrations
+                .iter()
+                .map(|m| (m.version, &*m.name, m.down.as_deref().map(str::trim)))
+                .collect::<Vec<_>>(),
+            [(1, "test", Some("-- Undoes the test!"))]
+        );
+    }
+
+    #[test]
+    fn diesel_layout() {
+        let migrations = ::nomad::nomad_migrations!("./tests/diesel");
+
+        assert_eq!(
+            migrations
+                .iter()
+                .map(|m| (m.version, &*m.name, m.sql.trim(), m.down.as_deref().map(str::trim)))
+                .collect::<Vec<_>>(),
+            [
+                (
+                    20201019025309,
+                    "create_users",
+                    "CREATE TABLE users (id integer primary key);",
+                    Some("DROP TABLE users;")
+                ),
+                (
+                    20201020090000,
+                    "create_posts",
+                    "CREATE TABLE posts (id integer primary key, user_id integer not null references users(id));",
+                    Some("DROP TABLE posts;")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn options() {
+        let migrations = ::nomad::nomad_migrations!(
+            path = "./tests/options",
+            pattern = r"^(?P<version>\d+)-(?P<name>[a-z_]+)(?P<down>\.undo)?\.sql$",
+            ignore = ["*draft*"],
+        );
+
+        assert_eq!(
+            migrations
+                .iter()
+                .map(|m| (
+                    m.version,
+                    &*m.name,
+                    m.sql.trim(),
+                    m.down.as_deref().map(str::trim)
+                ))
+                .collect::<Vec<_>>(),
+            [(
+                1,
+                "create_users",
+                "CREATE TABLE users (id integer primary key);",
+                Some("DROP TABLE users;")
+            )]
+        );
+    }
+
+    #[test]
+    fn conventions() {
+        let expected = [
+            (1, "create_users", Some("DROP TABLE users;")),
+            (2, "create_posts", None),
+        ];
+
+        let flyway = ::nomad::nomad_migrations!(path = "./tests/flyway", convention = "flyway");
+        let golang =
+            ::nomad::nomad_migrations!(path = "./tests/golang", convention = "golang-migrate");
+
+        for migrations in [flyway.as_ref(), golang.as_ref()].iter() {
+            assert_eq!(
+                migrations
+                    .iter()
+                    .map(|m| (m.version, &*m.name, m.down.as_deref().map(str::trim)))
+                    .collect::<Vec<_>>(),
+                expected
+            );
+        }
+
+        let diesel = ::nomad::nomad_migrations!(path = "./tests/diesel", convention = "diesel");
+        assert_eq!(
+            diesel.iter().map(|m| m.version).collect::<Vec<_>>(),
+            [20201019025309, 20201020090000]
+        );
+    }
+
+    #[test]
+    fn namespaces() {
+        let namespaces = ::nomad::nomad_namespaces!("./tests/namespaces");
+
+        assert_eq!(
+            namespaces
+                .iter()
+                .map(|(namespace, migrations)| (
+                    *namespace,
+                    migrations
+                        .iter()
+                        .map(|m| (m.version, &*m.name, m.down.is_some()))
+                        .collect::<Vec<_>>()
+                ))
+                .collect::<Vec<_>>(),
+            [
+                ("nomad", vec![(1, "settings", false)]),
+                (
+                    "billing",
+                    vec![(1, "create_invoices", false), (2, "create_payments", true)]
+                ),
+                ("billing/reports", vec![(1, "create_revenue", false)]),
+                ("core", vec![(1, "create_users", false)]),
+            ]
+        );
+    }
+
+    #[cfg(feature = "rusqlite")]
+    #[test]
+    fn migrate_namespaces() {
+        let mut conn = ::rusqlite::Connection::open_in_memory().expect("Failed to open database");
+
+        let mut registry = ::nomad::MigrationRegistry::new();
+        registry
+            .register_all(::nomad::nomad_namespaces!("./tests/namespaces"))
+            .expect("Namespaces should be unique");
+
+        let reports = registry.migrate_all(&mut conn).expect("Failed to migrate");
+        assert_eq!(
+            reports
+                .iter()
+                .map(|report| (report.namespace, report.last_executed()))
+                .collect::<Vec<_>>(),
+            [
+                ("nomad", Some(1)),
+                ("billing", Some(2)),
+                ("billing/reports", Some(1)),
+                ("core", Some(1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_namespace() {
+        let mut registry = ::nomad::MigrationRegistry::new();
+        registry
+            .register(
+                "core",
+                ::nomad::nomad_migrations!("./tests/namespaces/core"),
+            )
+            .expect("Namespaces should be unique");
+
+        match registry.register_all(::nomad::nomad_namespaces!("./tests/namespaces")) {
+            Err(err) => assert_eq!(err.namespace, "core"),
+            Ok(_) => panic!("Expected a duplicate namespace error"),
+        }
+
+        // Nothing from a failed batch is registered
+        assert_eq!(registry.namespaces().collect::<Vec<_>>(), ["core"]);
+    }
 }
 
 mod ordered {
@@ -224,11 +2860,15 @@ mod ordered {
         const MIGRATIONS: [Migration; 2] = [
             Migration {
                 version: 2,
-                sql: "-- test migration 2",
+                name: ::std::borrow::Cow::Borrowed("test_migration_2"),
+                sql: ::std::borrow::Cow::Borrowed("-- test migration 2"),
+                down: None,
             },
             Migration {
                 version: 1,
-                sql: "-- test migration 1",
+                name: ::std::borrow::Cow::Borrowed("test_migration_1"),
+                sql: ::std::borrow::Cow::Borrowed("-- test migration 1"),
+                down: None,
             },
         ];
 
@@ -245,22 +2885,30 @@ mod ordered {
         const SORTED_MIGRATIONS: [Migration; 2] = [
             Migration {
                 version: 1,
-                sql: "-- test migration 1",
+                name: ::std::borrow::Cow::Borrowed("test_migration_1"),
+                sql: ::std::borrow::Cow::Borrowed("-- test migration 1"),
+                down: None,
             },
             Migration {
                 version: 2,
-                sql: "-- test migration 2",
+                name: ::std::borrow::Cow::Borrowed("test_migration_2"),
+                sql: ::std::borrow::Cow::Borrowed("-- test migration 2"),
+                down: None,
             },
         ];
 
         const UNSORTED_MIGRATIONS: [Migration; 2] = [
             Migration {
                 version: 2,
-                sql: "-- test migration 2",
+                name: ::std::borrow::Cow::Borrowed("test_migration_2"),
+                sql: ::std::borrow::Cow::Borrowed("-- test migration 2"),
+                down: None,
             },
             Migration {
                 version: 1,
-                sql: "-- test migration 1",
+                name: ::std::borrow::Cow::Borrowed("test_migration_1"),
+                sql: ::std::borrow::Cow::Borrowed("-- test migration 1"),
+                down: None,
             },
         ];
 
@@ -275,3 +2923,29 @@ mod ordered {
         }
     }
 }
+
+mod timestamp {
+    use ::nomad::Timestamp;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn from_version() {
+        let timestamp = Timestamp::from_version(20200229235959).expect("Should be a timestamp");
+
+        assert_eq!(timestamp.to_string(), "2020-02-29 23:59:59");
+        assert_eq!(timestamp.version(), 20200229235959);
+
+        assert_eq!(Timestamp::from_version(5), None);
+        assert_eq!(Timestamp::from_version(20190229000000), None);
+        assert_eq!(Timestamp::from_version(20201019250000), None);
+    }
+
+    #[test]
+    fn from_system_time() {
+        let time = UNIX_EPOCH + Duration::from_secs(1603075989);
+        assert_eq!(Timestamp::from_system_time(time).version(), 20201019025309);
+
+        let time = UNIX_EPOCH + Duration::from_secs(951868799);
+        assert_eq!(Timestamp::from_system_time(time).version(), 20000229235959);
+    }
+}
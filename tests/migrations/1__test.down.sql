@@ -0,0 +1 @@
+-- Undoes the test!
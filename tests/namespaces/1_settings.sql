@@ -0,0 +1 @@
+CREATE TABLE settings (key text primary key, value text);
@@ -0,0 +1 @@
+CREATE TABLE payments (id integer primary key);
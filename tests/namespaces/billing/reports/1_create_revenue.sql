@@ -0,0 +1 @@
+CREATE VIEW revenue AS SELECT count(*) AS total FROM payments;
@@ -0,0 +1 @@
+CREATE TABLE drafts (id integer primary key);
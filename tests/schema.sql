@@ -0,0 +1,5 @@
+CREATE TABLE posts (id integer primary key, title text default 'a  b');
+
+CREATE TABLE users (id integer primary key, email text);
+
+CREATE UNIQUE INDEX users_email ON users(email);